@@ -9,11 +9,32 @@ CREATE TABLE IF NOT EXISTS service_requests (
     unique_key BIGINT PRIMARY KEY,
     created_at TIMESTAMPTZ NOT NULL,
     closed_at TIMESTAMPTZ,
+    agency TEXT,
     complaint_type TEXT NOT NULL,
+    complaint_category TEXT,
+    complaint_subcategory TEXT,
     descriptor TEXT,
+    resolution_description TEXT,
     borough TEXT CHECK (borough IN ('BRONX', 'BROOKLYN', 'MANHATTAN', 'QUEENS', 'STATEN ISLAND')),
     latitude DOUBLE PRECISION,
     longitude DOUBLE PRECISION,
+    x_coordinate_state_plane DOUBLE PRECISION,
+    y_coordinate_state_plane DOUBLE PRECISION,
+    coordinate_repair TEXT CHECK (coordinate_repair IN ('swapped', 'placeholder_removed', 'state_plane_converted')),
+    resolution_hours DOUBLE PRECISION,
+    created_local_date DATE,
+    created_hour SMALLINT,
+    day_of_week SMALLINT,
+    is_open BOOLEAN,
+    is_weekend BOOLEAN,
+    geohash TEXT,
+    hex_cell TEXT,
+    quality_flags INTEGER NOT NULL DEFAULT 0,
+    failed_rules TEXT[] NOT NULL DEFAULT '{}',
+    corrections JSONB NOT NULL DEFAULT '[]',
+    attributes JSONB NOT NULL DEFAULT '{}',
+    row_hash TEXT,
+    updated_at TIMESTAMPTZ,
     ingested_at TIMESTAMPTZ DEFAULT now()
 );
 
@@ -24,6 +45,73 @@ CREATE INDEX IF NOT EXISTS idx_service_requests_borough
     ON service_requests(borough);
 CREATE INDEX IF NOT EXISTS idx_service_requests_complaint_type 
     ON service_requests(complaint_type);
+CREATE INDEX IF NOT EXISTS idx_service_requests_complaint_category 
+    ON service_requests(complaint_category);
+CREATE INDEX IF NOT EXISTS idx_service_requests_created_local_date 
+    ON service_requests(created_local_date);
+CREATE INDEX IF NOT EXISTS idx_service_requests_day_hour 
+    ON service_requests(day_of_week, created_hour);
+CREATE INDEX IF NOT EXISTS idx_service_requests_open 
+    ON service_requests(created_at) WHERE is_open;
+CREATE INDEX IF NOT EXISTS idx_service_requests_geohash 
+    ON service_requests(geohash text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_service_requests_hex_cell 
+    ON service_requests(hex_cell);
+CREATE INDEX IF NOT EXISTS idx_service_requests_pristine 
+    ON service_requests(created_at) WHERE quality_flags = 0;
+
+-- Records held back by the quarantine rule policy; same columns without
+-- the CHECK constraints the failing values may violate
+CREATE TABLE IF NOT EXISTS service_requests_quarantine (
+    LIKE service_requests INCLUDING DEFAULTS,
+    PRIMARY KEY (unique_key)
+);
+
+-- One row per version of a request (SCD type 2), maintained by the loader
+-- whenever a stored row is inserted or changes; valid_to is NULL for the
+-- current version
+CREATE TABLE IF NOT EXISTS service_request_history (
+    history_id BIGSERIAL PRIMARY KEY,
+    unique_key BIGINT NOT NULL,
+    valid_from TIMESTAMPTZ NOT NULL,
+    valid_to TIMESTAMPTZ,
+    closed_at TIMESTAMPTZ,
+    agency TEXT,
+    complaint_type TEXT NOT NULL,
+    complaint_category TEXT,
+    descriptor TEXT,
+    resolution_description TEXT,
+    borough TEXT,
+    is_open BOOLEAN,
+    resolution_hours DOUBLE PRECISION,
+    row_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_service_request_history_key
+    ON service_request_history(unique_key, valid_from);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_service_request_history_current
+    ON service_request_history(unique_key) WHERE valid_to IS NULL;
+
+-- Decode table for service_requests.quality_flags bits (kept in sync with QualityFlags)
+CREATE TABLE IF NOT EXISTS quality_flag_definitions (
+    bit INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+INSERT INTO quality_flag_definitions (bit, name) VALUES
+    (1, 'borough_inferred'),
+    (2, 'coordinates_repaired'),
+    (4, 'missing_closed_date'),
+    (8, 'descriptor_truncated'),
+    (16, 'missing_coordinates'),
+    (32, 'complaint_type_corrected'),
+    (64, 'agency_corrected'),
+    (128, 'unmapped_complaint_type'),
+    (256, 'field_nullified'),
+    (512, 'rule_flagged'),
+    (1024, 'quarantined'),
+    (2048, 'text_truncated')
+ON CONFLICT (bit) DO UPDATE SET name = EXCLUDED.name;
 
 -- Create ETL watermarks table for incremental loads
 CREATE TABLE IF NOT EXISTS etl_watermarks (
@@ -32,59 +120,71 @@ CREATE TABLE IF NOT EXISTS etl_watermarks (
     last_created_at TIMESTAMPTZ,
     last_unique_key BIGINT,
     run_mode TEXT NOT NULL CHECK (run_mode IN ('full', 'incremental')),
+    input TEXT,
     rows_processed BIGINT NOT NULL DEFAULT 0,
     rows_inserted BIGINT NOT NULL DEFAULT 0,
     rows_skipped BIGINT NOT NULL DEFAULT 0,
     started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
     completed_at TIMESTAMPTZ,
-    status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'failed'))
+    status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'failed')),
+    error_message TEXT
 );
 
+CREATE UNIQUE INDEX IF NOT EXISTS idx_etl_watermarks_run_id ON etl_watermarks(run_id);
+
 -- Create materialized view for complaints by day and borough
 CREATE MATERIALIZED VIEW IF NOT EXISTS mv_complaints_by_day_borough AS
 SELECT 
-    DATE(created_at) as complaint_date,
+    created_local_date as complaint_date,
     borough,
     COUNT(*) as complaint_count
 FROM service_requests
 WHERE borough IS NOT NULL
-GROUP BY DATE(created_at), borough
+GROUP BY created_local_date, borough
 ORDER BY complaint_date DESC, borough;
 
 -- Create index on materialized view
 CREATE INDEX IF NOT EXISTS idx_mv_complaints_date_borough 
     ON mv_complaints_by_day_borough(complaint_date, borough);
 
--- Create materialized view for complaints by type and month
+-- Create materialized view for complaints by canonical category and month
+-- (unmapped complaint types fall back to the raw value)
 CREATE MATERIALIZED VIEW IF NOT EXISTS mv_complaints_by_type_month AS
 SELECT 
-    DATE_TRUNC('month', created_at) as month,
-    complaint_type,
+    DATE_TRUNC('month', created_local_date)::date as month,
+    COALESCE(complaint_category, complaint_type) as complaint_category,
+    complaint_subcategory,
     COUNT(*) as complaint_count,
-    AVG(EXTRACT(EPOCH FROM (closed_at - created_at))/3600) as avg_resolution_hours
+    AVG(resolution_hours) as avg_resolution_hours
 FROM service_requests
-WHERE complaint_type IS NOT NULL
-GROUP BY DATE_TRUNC('month', created_at), complaint_type
+GROUP BY 1, 2, 3
 ORDER BY month DESC, complaint_count DESC;
 
 -- Create index on materialized view
 CREATE INDEX IF NOT EXISTS idx_mv_complaints_type_month 
-    ON mv_complaints_by_type_month(month, complaint_type);
+    ON mv_complaints_by_type_month(month, complaint_category);
 
 -- Grant permissions (adjust as needed for production)
 -- These will be executed if the roles exist
 DO $$
 BEGIN
     IF EXISTS (SELECT FROM pg_roles WHERE rolname = 'ingest_role') THEN
-        GRANT INSERT, SELECT ON service_requests TO ingest_role;
-        GRANT SELECT, UPDATE ON etl_watermarks TO ingest_role;
+        GRANT INSERT, SELECT, UPDATE ON service_requests TO ingest_role;
+        GRANT INSERT, SELECT, UPDATE ON service_request_history TO ingest_role;
+        GRANT INSERT, SELECT, UPDATE ON service_requests_quarantine TO ingest_role;
+        GRANT USAGE ON SEQUENCE service_request_history_history_id_seq TO ingest_role;
+        GRANT INSERT, SELECT, UPDATE ON etl_watermarks TO ingest_role;
+        GRANT USAGE ON SEQUENCE etl_watermarks_id_seq TO ingest_role;
     END IF;
     
     IF EXISTS (SELECT FROM pg_roles WHERE rolname = 'report_role') THEN
         GRANT SELECT ON service_requests TO report_role;
+        GRANT SELECT ON service_request_history TO report_role;
+        GRANT SELECT ON service_requests_quarantine TO report_role;
         GRANT SELECT ON mv_complaints_by_day_borough TO report_role;
         GRANT SELECT ON mv_complaints_by_type_month TO report_role;
         GRANT SELECT ON etl_watermarks TO report_role;
+        GRANT SELECT ON quality_flag_definitions TO report_role;
     END IF;
 END
 $$;
@@ -0,0 +1,134 @@
+// Coordinate repair - swapped axes, placeholder points and State Plane fallback
+use crate::clean::validator::Validator;
+use crate::db::schema::ServiceRequest;
+use crate::geo::state_plane_to_wgs84;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateRepair {
+    /// Latitude and longitude were supplied in each other's columns
+    Swapped,
+    /// `0` placeholder coordinates were removed
+    PlaceholderRemoved,
+    /// Coordinates were derived from the State Plane (EPSG:2263) columns
+    StatePlaneConverted,
+}
+
+impl CoordinateRepair {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoordinateRepair::Swapped => "swapped",
+            CoordinateRepair::PlaceholderRemoved => "placeholder_removed",
+            CoordinateRepair::StatePlaneConverted => "state_plane_converted",
+        }
+    }
+}
+
+/// Repairs the coordinates of `record` in place and returns the repair that
+/// was applied, if any. Coordinates that cannot be repaired are left for
+/// validation to reject.
+pub fn repair_coordinates(
+    record: &mut ServiceRequest,
+    validator: &Validator,
+) -> Option<CoordinateRepair> {
+    let mut repair = None;
+
+    if let (Some(lat), Some(lon)) = (record.latitude, record.longitude) {
+        if is_placeholder(lat) || is_placeholder(lon) {
+            record.latitude = None;
+            record.longitude = None;
+            repair = Some(CoordinateRepair::PlaceholderRemoved);
+        } else if !validator.validate_coordinates(lat, lon)
+            && validator.validate_coordinates(lon, lat)
+        {
+            record.latitude = Some(lon);
+            record.longitude = Some(lat);
+            return Some(CoordinateRepair::Swapped);
+        } else {
+            return None;
+        }
+    }
+
+    if record.latitude.is_none() || record.longitude.is_none() {
+        if let (Some(x), Some(y)) = (
+            record.x_coordinate_state_plane,
+            record.y_coordinate_state_plane,
+        ) {
+            if !is_placeholder(x) && !is_placeholder(y) {
+                let (lat, lon) = state_plane_to_wgs84(x, y);
+                if validator.validate_coordinates(lat, lon) {
+                    record.latitude = Some(lat);
+                    record.longitude = Some(lon);
+                    repair = Some(CoordinateRepair::StatePlaneConverted);
+                }
+            }
+        }
+    }
+
+    repair
+}
+
+fn is_placeholder(value: f64) -> bool {
+    value == 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        lat: Option<f64>,
+        lon: Option<f64>,
+        x: Option<f64>,
+        y: Option<f64>,
+    ) -> ServiceRequest {
+        ServiceRequest {
+            unique_key: 1,
+            latitude: lat,
+            longitude: lon,
+            x_coordinate_state_plane: x,
+            y_coordinate_state_plane: y,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_repair_swapped_coordinates() {
+        let validator = Validator::new();
+        let mut swapped = record(Some(-73.9855), Some(40.7580), None, None);
+
+        let repair = repair_coordinates(&mut swapped, &validator);
+
+        assert_eq!(repair, Some(CoordinateRepair::Swapped));
+        assert_eq!(swapped.latitude, Some(40.7580));
+        assert_eq!(swapped.longitude, Some(-73.9855));
+    }
+
+    #[test]
+    fn test_repair_placeholder_falls_back_to_state_plane() {
+        let validator = Validator::new();
+
+        let mut zeroed = record(Some(0.0), Some(0.0), None, None);
+        assert_eq!(
+            repair_coordinates(&mut zeroed, &validator),
+            Some(CoordinateRepair::PlaceholderRemoved)
+        );
+        assert_eq!(zeroed.latitude, None);
+        assert_eq!(zeroed.longitude, None);
+
+        let mut zeroed_with_xy = record(Some(0.0), Some(0.0), Some(988_300.0), Some(215_500.0));
+        assert_eq!(
+            repair_coordinates(&mut zeroed_with_xy, &validator),
+            Some(CoordinateRepair::StatePlaneConverted)
+        );
+        assert!(zeroed_with_xy.latitude.is_some());
+    }
+
+    #[test]
+    fn test_repair_leaves_valid_coordinates() {
+        let validator = Validator::new();
+        let mut valid = record(Some(40.7580), Some(-73.9855), Some(1.0), Some(1.0));
+
+        assert_eq!(repair_coordinates(&mut valid, &validator), None);
+        assert_eq!(valid.latitude, Some(40.7580));
+    }
+}
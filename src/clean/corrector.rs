@@ -0,0 +1,293 @@
+// Alias and edit-distance correction of misspelled categorical values
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::clean::taxonomy::canonical_key;
+use crate::clean::validator::VALID_BOROUGHS;
+
+pub const DEFAULT_CORRECTION_THRESHOLD: f64 = 0.8;
+
+const BOROUGH_ALIASES: &[(&str, &str)] = &[
+    ("BK", "BROOKLYN"),
+    ("BKLYN", "BROOKLYN"),
+    ("KINGS", "BROOKLYN"),
+    ("BX", "BRONX"),
+    ("THE BRONX", "BRONX"),
+    ("MN", "MANHATTAN"),
+    ("NEW YORK", "MANHATTAN"),
+    ("QN", "QUEENS"),
+    ("SI", "STATEN ISLAND"),
+    ("STATEN IS", "STATEN ISLAND"),
+    ("RICHMOND", "STATEN ISLAND"),
+];
+
+const AGENCIES: &[&str] = &[
+    "3-1-1", "DCA", "DCWP", "DEP", "DFTA", "DHS", "DOB", "DOE", "DOF", "DOHMH", "DOITT", "DOT",
+    "DPR", "DSNY", "EDC", "HPD", "HRA", "NYPD", "OSE", "TLC",
+];
+
+const AGENCY_ALIASES: &[(&str, &str)] = &[
+    ("POLICE", "NYPD"),
+    ("NEW YORK CITY POLICE DEPARTMENT", "NYPD"),
+    ("SANITATION", "DSNY"),
+    ("DEPARTMENT OF SANITATION", "DSNY"),
+    ("DEPARTMENT OF TRANSPORTATION", "DOT"),
+    ("DEPARTMENT OF BUILDINGS", "DOB"),
+    ("HOUSING PRESERVATION AND DEVELOPMENT", "HPD"),
+    ("PARKS", "DPR"),
+    ("HEALTH", "DOHMH"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorrectionMethod {
+    Alias,
+    EditDistance,
+}
+
+/// Audit record for a categorical value that was rewritten during transform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldCorrection {
+    pub field: String,
+    pub original: String,
+    pub corrected: String,
+    pub method: CorrectionMethod,
+    pub confidence: f64,
+}
+
+/// Extra aliases per field, loaded from a JSON file of the form
+/// `{"borough": {"BKN": "BROOKLYN"}, "agency": {...}, "complaint_type": {...}}`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CorrectionAliases {
+    #[serde(default)]
+    pub borough: BTreeMap<String, String>,
+    #[serde(default)]
+    pub agency: BTreeMap<String, String>,
+    #[serde(default)]
+    pub complaint_type: BTreeMap<String, String>,
+}
+
+impl CorrectionAliases {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read correction aliases file: {}", path))?;
+        serde_json::from_str(&content).context("Failed to parse correction aliases file")
+    }
+}
+
+/// Maps misspelled values of a single categorical field onto a fixed
+/// vocabulary, either through an explicit alias or the closest vocabulary
+/// entry by edit distance.
+#[derive(Debug, Clone)]
+pub struct CategoricalCorrector {
+    field: &'static str,
+    vocabulary: HashMap<String, String>,
+    aliases: HashMap<String, String>,
+    threshold: f64,
+}
+
+impl CategoricalCorrector {
+    pub fn new<S: AsRef<str>>(field: &'static str, vocabulary: &[S], threshold: f64) -> Self {
+        Self {
+            field,
+            vocabulary: vocabulary
+                .iter()
+                .map(|v| (canonical_key(v.as_ref()), v.as_ref().to_string()))
+                .collect(),
+            aliases: HashMap::new(),
+            threshold,
+        }
+    }
+
+    pub fn with_alias(mut self, alias: &str, value: &str) -> Self {
+        self.aliases
+            .insert(canonical_key(alias), value.trim().to_string());
+        self
+    }
+
+    pub fn with_aliases<'a, I>(self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        aliases.into_iter().fold(self, |corrector, (alias, value)| {
+            corrector.with_alias(alias, value)
+        })
+    }
+
+    /// Returns a correction for `raw`, or `None` when the value already names a
+    /// vocabulary entry or no candidate clears the confidence threshold.
+    pub fn correct(&self, raw: &str) -> Option<FieldCorrection> {
+        let key = canonical_key(raw);
+        if key.is_empty() || self.vocabulary.contains_key(&key) {
+            return None;
+        }
+
+        if let Some(value) = self.aliases.get(&key) {
+            return Some(self.correction(raw, value, CorrectionMethod::Alias, 1.0));
+        }
+
+        let (value, confidence) = self.closest(&key)?;
+        if confidence < self.threshold {
+            return None;
+        }
+
+        Some(self.correction(raw, value, CorrectionMethod::EditDistance, confidence))
+    }
+
+    fn closest(&self, key: &str) -> Option<(&String, f64)> {
+        let mut best: Option<(&String, f64)> = None;
+        let mut ambiguous = false;
+
+        for (candidate, value) in &self.vocabulary {
+            let confidence = similarity(key, candidate);
+            match best {
+                Some((_, best_confidence)) if confidence < best_confidence => {}
+                Some((_, best_confidence)) if confidence == best_confidence => ambiguous = true,
+                _ => {
+                    best = Some((value, confidence));
+                    ambiguous = false;
+                }
+            }
+        }
+
+        // Two equally close candidates means we cannot tell which was intended
+        if ambiguous {
+            None
+        } else {
+            best
+        }
+    }
+
+    fn correction(
+        &self,
+        raw: &str,
+        value: &str,
+        method: CorrectionMethod,
+        confidence: f64,
+    ) -> FieldCorrection {
+        FieldCorrection {
+            field: self.field.to_string(),
+            original: raw.to_string(),
+            corrected: value.to_string(),
+            method,
+            confidence,
+        }
+    }
+}
+
+/// Correctors for every categorical field the transform stage repairs.
+#[derive(Debug, Clone)]
+pub struct FieldCorrectors {
+    pub borough: CategoricalCorrector,
+    pub agency: CategoricalCorrector,
+    pub complaint_type: Option<CategoricalCorrector>,
+}
+
+impl FieldCorrectors {
+    /// Builds correctors for borough and agency from the built-in vocabularies,
+    /// and for complaint type when a vocabulary (typically the complaint types
+    /// named in the taxonomy) is supplied.
+    pub fn new<S: AsRef<str>>(
+        threshold: f64,
+        aliases: &CorrectionAliases,
+        complaint_types: &[S],
+    ) -> Self {
+        let borough = CategoricalCorrector::new("borough", VALID_BOROUGHS, threshold)
+            .with_aliases(BOROUGH_ALIASES.iter().copied())
+            .with_aliases(alias_pairs(&aliases.borough));
+
+        let agency = CategoricalCorrector::new("agency", AGENCIES, threshold)
+            .with_aliases(AGENCY_ALIASES.iter().copied())
+            .with_aliases(alias_pairs(&aliases.agency));
+
+        let complaint_type = (!complaint_types.is_empty()).then(|| {
+            CategoricalCorrector::new("complaint_type", complaint_types, threshold)
+                .with_aliases(alias_pairs(&aliases.complaint_type))
+        });
+
+        Self {
+            borough,
+            agency,
+            complaint_type,
+        }
+    }
+}
+
+fn alias_pairs(aliases: &BTreeMap<String, String>) -> impl Iterator<Item = (&str, &str)> {
+    aliases.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+}
+
+/// Normalized similarity in `[0, 1]` derived from Levenshtein distance.
+fn similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / max_len as f64
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut current = vec![0; b_chars.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn borough_corrector() -> CategoricalCorrector {
+        let no_complaint_types: &[&str] = &[];
+        FieldCorrectors::new(
+            DEFAULT_CORRECTION_THRESHOLD,
+            &CorrectionAliases::default(),
+            no_complaint_types,
+        )
+        .borough
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(levenshtein("BROOKLN", "BROOKLYN"), 1);
+        assert_eq!(levenshtein("", "ABC"), 3);
+        assert_eq!(levenshtein("KITTEN", "SITTING"), 3);
+    }
+
+    #[test]
+    fn test_correct_misspelled_borough() {
+        let corrector = borough_corrector();
+
+        let brooklyn = corrector.correct("BROOKLN").unwrap();
+        assert_eq!(brooklyn.corrected, "BROOKLYN");
+        assert_eq!(brooklyn.method, CorrectionMethod::EditDistance);
+        assert_eq!(brooklyn.original, "BROOKLN");
+
+        let queens = corrector.correct("Queen").unwrap();
+        assert_eq!(queens.corrected, "QUEENS");
+
+        let staten_island = corrector.correct("Staten Is.").unwrap();
+        assert_eq!(staten_island.corrected, "STATEN ISLAND");
+        assert_eq!(staten_island.method, CorrectionMethod::Alias);
+    }
+
+    #[test]
+    fn test_correct_leaves_valid_and_unknown_values() {
+        let corrector = borough_corrector();
+        assert!(corrector.correct("manhattan").is_none());
+        assert!(corrector.correct("CHICAGO").is_none());
+    }
+}
@@ -1,5 +1,23 @@
 // Data cleaning utilities
+pub mod coordinates;
+pub mod corrector;
+pub mod normalize;
+pub mod outliers;
+pub mod quality;
+pub mod redact;
+pub mod rules;
+pub mod sentinel;
+pub mod taxonomy;
 pub mod validator;
 
 // Re-exports
+pub use coordinates::*;
+pub use corrector::*;
+pub use normalize::*;
+pub use outliers::*;
+pub use quality::*;
+pub use redact::*;
+pub use rules::*;
+pub use sentinel::*;
+pub use taxonomy::*;
 pub use validator::*;
@@ -0,0 +1,386 @@
+// Text normalization - Unicode, whitespace, casing and length limits
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, ensure, Result};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::clean::quality::QualityFlags;
+use crate::db::schema::ServiceRequest;
+
+/// Invisible format characters that split otherwise identical values.
+const INVISIBLE_CHARS: &[char] = &[
+    '\u{00AD}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}',
+];
+
+/// Text fields normalization applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TextField {
+    Agency,
+    ComplaintType,
+    Descriptor,
+    ResolutionDescription,
+    Borough,
+}
+
+impl TextField {
+    pub const ALL: &'static [TextField] = &[
+        TextField::Agency,
+        TextField::ComplaintType,
+        TextField::Descriptor,
+        TextField::ResolutionDescription,
+        TextField::Borough,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextField::Agency => "agency",
+            TextField::ComplaintType => "complaint_type",
+            TextField::Descriptor => "descriptor",
+            TextField::ResolutionDescription => "resolution_description",
+            TextField::Borough => "borough",
+        }
+    }
+
+    /// Flag set when the field is cut to its maximum length.
+    fn truncation_flag(&self) -> QualityFlags {
+        match self {
+            TextField::Descriptor => QualityFlags::DESCRIPTOR_TRUNCATED,
+            _ => QualityFlags::TEXT_TRUNCATED,
+        }
+    }
+
+    fn value_mut<'a>(&self, record: &'a mut ServiceRequest) -> Option<&'a mut String> {
+        match self {
+            TextField::Agency => record.agency.as_mut(),
+            TextField::ComplaintType => Some(&mut record.complaint_type),
+            TextField::Descriptor => record.descriptor.as_mut(),
+            TextField::ResolutionDescription => record.resolution_description.as_mut(),
+            TextField::Borough => record.borough.as_mut(),
+        }
+    }
+}
+
+impl fmt::Display for TextField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TextField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        TextField::ALL
+            .iter()
+            .copied()
+            .find(|field| field.as_str() == s.trim())
+            .ok_or_else(|| anyhow!("Unknown text field: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Casing {
+    #[default]
+    Preserve,
+    Upper,
+    Lower,
+    /// First letter of each word upper case, the rest lower case
+    Title,
+}
+
+impl Casing {
+    fn apply(&self, text: &str) -> String {
+        match self {
+            Casing::Preserve => text.to_string(),
+            Casing::Upper => text.to_uppercase(),
+            Casing::Lower => text.to_lowercase(),
+            Casing::Title => {
+                let mut result = String::with_capacity(text.len());
+                let mut word_start = true;
+                for c in text.chars() {
+                    if word_start {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric() && c != '\'';
+                }
+                result
+            }
+        }
+    }
+}
+
+impl FromStr for Casing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "preserve" => Ok(Casing::Preserve),
+            "upper" => Ok(Casing::Upper),
+            "lower" => Ok(Casing::Lower),
+            "title" => Ok(Casing::Title),
+            other => Err(anyhow!("Unknown casing: {}", other)),
+        }
+    }
+}
+
+/// NFC-normalizes `text`, drops control and invisible format characters
+/// (line breaks and tabs become spaces) and collapses whitespace runs.
+pub fn normalize_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut pending_space = false;
+
+    for c in text.nfc() {
+        if c.is_whitespace() {
+            pending_space = true;
+        } else if c.is_control() || INVISIBLE_CHARS.contains(&c) {
+            continue;
+        } else {
+            if pending_space && !result.is_empty() {
+                result.push(' ');
+            }
+            pending_space = false;
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Per-field casing and maximum length applied after [`normalize_text`].
+#[derive(Debug, Clone)]
+pub struct TextNormalizer {
+    casing: BTreeMap<TextField, Casing>,
+    max_length: BTreeMap<TextField, usize>,
+}
+
+impl TextNormalizer {
+    /// Sets the casing of `field`. Boroughs must stay upper case, the only
+    /// form the service_requests CHECK constraint accepts.
+    pub fn with_casing(mut self, field: TextField, casing: Casing) -> Result<Self> {
+        ensure!(
+            field != TextField::Borough || casing == Casing::Upper,
+            "Field {} must stay upper case",
+            field
+        );
+        self.casing.insert(field, casing);
+        Ok(self)
+    }
+
+    pub fn with_max_length(mut self, field: TextField, max_length: Option<usize>) -> Self {
+        match max_length {
+            Some(length) => self.max_length.insert(field, length),
+            None => self.max_length.remove(&field),
+        };
+        self
+    }
+
+    /// Applies `field=casing` overrides, e.g. `complaint_type=title,descriptor=lower`.
+    pub fn parse_casing(mut self, spec: &str) -> Result<Self> {
+        for (field, casing) in parse_pairs(spec)? {
+            self = self.with_casing(field, casing.parse()?)?;
+        }
+        Ok(self)
+    }
+
+    /// Applies `field=length` overrides; `0` removes the limit.
+    pub fn parse_max_length(mut self, spec: &str) -> Result<Self> {
+        for (field, length) in parse_pairs(spec)? {
+            let length: usize = length
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid max length for {}: {}", field, length))?;
+            self = self.with_max_length(field, (length > 0).then_some(length));
+        }
+        Ok(self)
+    }
+
+    /// Normalizes `text` and applies the casing of `field`.
+    pub fn normalize(&self, field: TextField, text: &str) -> String {
+        let casing = self.casing.get(&field).copied().unwrap_or_default();
+        casing.apply(&normalize_text(text))
+    }
+
+    /// `text` cut to the maximum length of `field`, or `None` if it fits.
+    pub fn truncate(&self, field: TextField, text: &str) -> Option<String> {
+        let &max = self.max_length.get(&field)?;
+        if text.chars().count() <= max {
+            return None;
+        }
+        let truncated: String = text.chars().take(max).collect();
+        Some(truncated.trim_end().to_string())
+    }
+
+    /// Normalizes every text field of `record` in place, calling
+    /// `on_normalized` for each field that changed. Lengths are left alone:
+    /// truncation runs separately, after redaction, so PII straddling the
+    /// limit is never cut into a fragment the redactor no longer matches.
+    pub fn apply(&self, record: &mut ServiceRequest, mut on_normalized: impl FnMut(TextField)) {
+        for &field in TextField::ALL {
+            let Some(value) = field.value_mut(record) else {
+                continue;
+            };
+            let normalized = self.normalize(field, value);
+            if normalized != *value {
+                on_normalized(field);
+                *value = normalized;
+            }
+        }
+    }
+
+    /// Cuts every text field of `record` to its maximum length, setting
+    /// truncation flags and calling `on_truncated` for each field cut short.
+    pub fn apply_max_length(
+        &self,
+        record: &mut ServiceRequest,
+        mut on_truncated: impl FnMut(TextField),
+    ) {
+        let mut flags = QualityFlags::default();
+
+        for &field in TextField::ALL {
+            let Some(value) = field.value_mut(record) else {
+                continue;
+            };
+            if let Some(truncated) = self.truncate(field, value) {
+                flags.insert(field.truncation_flag());
+                on_truncated(field);
+                *value = truncated;
+            }
+        }
+
+        record.quality_flags.insert(flags);
+    }
+}
+
+impl Default for TextNormalizer {
+    /// Boroughs and agency codes are upper case; free text is capped so a
+    /// runaway value cannot bloat rows.
+    fn default() -> Self {
+        Self {
+            casing: BTreeMap::from([
+                (TextField::Agency, Casing::Upper),
+                (TextField::Borough, Casing::Upper),
+            ]),
+            max_length: BTreeMap::from([
+                (TextField::ComplaintType, 200),
+                (TextField::Descriptor, 500),
+                (TextField::ResolutionDescription, 2000),
+            ]),
+        }
+    }
+}
+
+fn parse_pairs(spec: &str) -> Result<Vec<(TextField, &str)>> {
+    spec.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (field, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected field=value, got: {}", pair))?;
+            ensure!(
+                !value.trim().is_empty(),
+                "Missing value for {}",
+                field.trim()
+            );
+            Ok((field.parse()?, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_text() {
+        // Decomposed "é", non-breaking space, zero-width space and a tab
+        assert_eq!(
+            normalize_text("  Cafe\u{0301}\u{00A0}\u{00A0}Noise\u{200B}\tLevel\u{0007} "),
+            "Café Noise Level"
+        );
+        assert_eq!(normalize_text("Loud\r\nMusic"), "Loud Music");
+    }
+
+    #[test]
+    fn test_casing_and_truncation() {
+        let normalizer = TextNormalizer::default()
+            .parse_casing("complaint_type=title")
+            .unwrap()
+            .parse_max_length("descriptor=10")
+            .unwrap();
+
+        assert_eq!(
+            normalizer.normalize(TextField::ComplaintType, "HEAT/HOT  WATER"),
+            "Heat/Hot Water"
+        );
+        assert_eq!(
+            normalizer.truncate(TextField::Descriptor, "Loud Music/Party"),
+            Some("Loud Music".to_string())
+        );
+        assert_eq!(
+            normalizer.truncate(TextField::Descriptor, "Loud Music"),
+            None
+        );
+        assert_eq!(
+            normalizer.normalize(TextField::Borough, "staten island"),
+            "STATEN ISLAND"
+        );
+    }
+
+    #[test]
+    fn test_apply_flags_truncation() {
+        let normalizer = TextNormalizer::default()
+            .parse_max_length("descriptor=4,resolution_description=4")
+            .unwrap();
+        let mut record = ServiceRequest {
+            descriptor: Some("Loud Music".to_string()),
+            resolution_description: Some("Closed".to_string()),
+            agency: Some("nypd".to_string()),
+            ..Default::default()
+        };
+
+        let mut normalized = Vec::new();
+        normalizer.apply(&mut record, |field| normalized.push(field));
+        assert_eq!(normalized, vec![TextField::Agency]);
+        assert_eq!(record.descriptor.as_deref(), Some("Loud Music"));
+        assert_eq!(record.quality_flags, QualityFlags::default());
+
+        let mut truncated = Vec::new();
+        normalizer.apply_max_length(&mut record, |field| truncated.push(field));
+        assert_eq!(
+            truncated,
+            vec![TextField::Descriptor, TextField::ResolutionDescription]
+        );
+        assert_eq!(record.descriptor.as_deref(), Some("Loud"));
+        assert_eq!(record.agency.as_deref(), Some("NYPD"));
+        assert!(record
+            .quality_flags
+            .contains(QualityFlags::DESCRIPTOR_TRUNCATED));
+        assert!(record.quality_flags.contains(QualityFlags::TEXT_TRUNCATED));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_entries() {
+        assert!(TextNormalizer::default()
+            .parse_casing("borough=shout")
+            .is_err());
+        assert!(TextNormalizer::default()
+            .parse_casing("borough=title")
+            .is_err());
+        assert!(TextNormalizer::default()
+            .parse_casing("borough=lower")
+            .is_err());
+        assert!(TextNormalizer::default()
+            .parse_casing("borough=upper")
+            .is_ok());
+        assert!(TextNormalizer::default()
+            .parse_casing("latitude=upper")
+            .is_err());
+        assert!(TextNormalizer::default()
+            .parse_max_length("descriptor=-1")
+            .is_err());
+    }
+}
@@ -0,0 +1,121 @@
+// Statistical outlier detection on resolution duration
+use std::collections::HashMap;
+
+use crate::clean::taxonomy::canonical_key;
+
+pub const DEFAULT_OUTLIER_Z_THRESHOLD: f64 = 3.5;
+pub const DEFAULT_OUTLIER_MIN_SAMPLES: u64 = 30;
+
+/// Welford's online mean and variance.
+#[derive(Debug, Clone, Copy, Default)]
+struct RunningStats {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningStats {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        (self.m2 / (self.count - 1) as f64).sqrt()
+    }
+}
+
+/// Flags unusually long resolution times per complaint type.
+///
+/// Resolution times are heavily right-skewed, so statistics are kept on
+/// `ln(1 + hours)`. A duration is an outlier when its z-score within its
+/// complaint type exceeds the threshold; only the long tail is flagged, as
+/// requests closed almost immediately are common and legitimate. Statistics
+/// accumulate over the whole run, and types with fewer than `min_samples`
+/// observations are never flagged.
+///
+/// Results depend on the order records are observed in: a duration is judged
+/// against the samples observed so far, so a record early in a run can pass
+/// where the same record later on would be flagged.
+#[derive(Debug, Clone)]
+pub struct ResolutionOutliers {
+    z_threshold: f64,
+    min_samples: u64,
+    by_type: HashMap<String, RunningStats>,
+}
+
+impl ResolutionOutliers {
+    pub fn new(z_threshold: f64, min_samples: u64) -> Self {
+        Self {
+            z_threshold,
+            min_samples,
+            by_type: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, complaint_type: &str, hours: f64) {
+        self.by_type
+            .entry(canonical_key(complaint_type))
+            .or_default()
+            .push(hours.max(0.0).ln_1p());
+    }
+
+    /// Z-score of `hours` within its complaint type, once enough samples exist.
+    pub fn z_score(&self, complaint_type: &str, hours: f64) -> Option<f64> {
+        let stats = self.by_type.get(&canonical_key(complaint_type))?;
+        let std_dev = stats.std_dev();
+        if stats.count < self.min_samples || std_dev == 0.0 {
+            return None;
+        }
+        Some((hours.max(0.0).ln_1p() - stats.mean) / std_dev)
+    }
+
+    pub fn is_outlier(&self, complaint_type: &str, hours: f64) -> bool {
+        self.z_score(complaint_type, hours)
+            .is_some_and(|z| z > self.z_threshold)
+    }
+}
+
+impl Default for ResolutionOutliers {
+    fn default() -> Self {
+        Self::new(DEFAULT_OUTLIER_Z_THRESHOLD, DEFAULT_OUTLIER_MIN_SAMPLES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_outliers_per_complaint_type() {
+        let mut outliers = ResolutionOutliers::new(3.5, 30);
+        for i in 0..100 {
+            outliers.observe("Noise", 2.0 + (i % 5) as f64);
+            outliers.observe("HEAT/HOT WATER", 48.0 + (i % 7) as f64 * 6.0);
+        }
+
+        // 40 years is an outlier for both types
+        assert!(outliers.is_outlier("noise", 350_000.0));
+        assert!(outliers.is_outlier("HEAT/HOT WATER", 350_000.0));
+        // Two days is typical for heat complaints but not for noise
+        assert!(outliers.is_outlier("Noise", 48.0));
+        assert!(!outliers.is_outlier("HEAT/HOT WATER", 48.0));
+        // Fast closures are never flagged
+        assert!(!outliers.is_outlier("HEAT/HOT WATER", 0.0));
+    }
+
+    #[test]
+    fn test_resolution_outliers_need_min_samples() {
+        let mut outliers = ResolutionOutliers::new(3.5, 30);
+        for i in 0..29 {
+            outliers.observe("Noise", 2.0 + (i % 5) as f64);
+        }
+        assert_eq!(outliers.z_score("Noise", 350_000.0), None);
+        assert!(!outliers.is_outlier("Graffiti", 350_000.0));
+    }
+}
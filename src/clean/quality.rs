@@ -0,0 +1,112 @@
+// Record-level data quality flags
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Bitmask of data quality issues found while transforming a record.
+///
+/// Stored as an INTEGER in `service_requests.quality_flags`; a value of `0`
+/// marks a pristine row. Bit positions are part of the table contract and
+/// must never be reused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct QualityFlags(i32);
+
+impl QualityFlags {
+    pub const BOROUGH_INFERRED: Self = Self(1 << 0);
+    pub const COORDINATES_REPAIRED: Self = Self(1 << 1);
+    pub const MISSING_CLOSED_DATE: Self = Self(1 << 2);
+    pub const DESCRIPTOR_TRUNCATED: Self = Self(1 << 3);
+    pub const MISSING_COORDINATES: Self = Self(1 << 4);
+    pub const COMPLAINT_TYPE_CORRECTED: Self = Self(1 << 5);
+    pub const AGENCY_CORRECTED: Self = Self(1 << 6);
+    pub const UNMAPPED_COMPLAINT_TYPE: Self = Self(1 << 7);
+    pub const FIELD_NULLIFIED: Self = Self(1 << 8);
+    pub const RULE_FLAGGED: Self = Self(1 << 9);
+    pub const QUARANTINED: Self = Self(1 << 10);
+    pub const TEXT_TRUNCATED: Self = Self(1 << 11);
+
+    /// Every defined flag with its name, in bit order.
+    pub const ALL: &'static [(QualityFlags, &'static str)] = &[
+        (Self::BOROUGH_INFERRED, "borough_inferred"),
+        (Self::COORDINATES_REPAIRED, "coordinates_repaired"),
+        (Self::MISSING_CLOSED_DATE, "missing_closed_date"),
+        (Self::DESCRIPTOR_TRUNCATED, "descriptor_truncated"),
+        (Self::MISSING_COORDINATES, "missing_coordinates"),
+        (Self::COMPLAINT_TYPE_CORRECTED, "complaint_type_corrected"),
+        (Self::AGENCY_CORRECTED, "agency_corrected"),
+        (Self::UNMAPPED_COMPLAINT_TYPE, "unmapped_complaint_type"),
+        (Self::FIELD_NULLIFIED, "field_nullified"),
+        (Self::RULE_FLAGGED, "rule_flagged"),
+        (Self::QUARANTINED, "quarantined"),
+        (Self::TEXT_TRUNCATED, "text_truncated"),
+    ];
+
+    pub fn bits(&self) -> i32 {
+        self.0
+    }
+
+    pub fn is_pristine(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, flag: QualityFlags) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+
+    pub fn insert(&mut self, flag: QualityFlags) {
+        self.0 |= flag.0;
+    }
+
+    pub fn set(&mut self, flag: QualityFlags, enabled: bool) {
+        if enabled {
+            self.0 |= flag.0;
+        } else {
+            self.0 &= !flag.0;
+        }
+    }
+
+    /// Names of the flags that are set.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        Self::ALL
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Display for QualityFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.names().collect::<Vec<_>>().join("|"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_flags_set_and_names() {
+        let mut flags = QualityFlags::default();
+        assert!(flags.is_pristine());
+
+        flags.insert(QualityFlags::BOROUGH_INFERRED);
+        flags.set(QualityFlags::MISSING_CLOSED_DATE, true);
+        assert_eq!(flags.bits(), 0b101);
+        assert_eq!(flags.to_string(), "borough_inferred|missing_closed_date");
+
+        flags.set(QualityFlags::BOROUGH_INFERRED, false);
+        assert!(!flags.contains(QualityFlags::BOROUGH_INFERRED));
+        assert!(flags.contains(QualityFlags::MISSING_CLOSED_DATE));
+    }
+
+    #[test]
+    fn test_quality_flag_bits_are_unique() {
+        let combined = QualityFlags::ALL.iter().fold(0, |acc, (flag, _)| {
+            assert_eq!(acc & flag.bits(), 0);
+            acc | flag.bits()
+        });
+        assert_eq!(combined.count_ones() as usize, QualityFlags::ALL.len());
+    }
+}
@@ -0,0 +1,172 @@
+// PII redaction for free-text fields
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PiiKind {
+    Email,
+    Ssn,
+    Phone,
+    Unit,
+}
+
+impl PiiKind {
+    /// Detectors run in this order; emails and SSNs go first so their digits
+    /// are not mistaken for phone numbers.
+    pub const ALL: &'static [PiiKind] =
+        &[PiiKind::Email, PiiKind::Ssn, PiiKind::Phone, PiiKind::Unit];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Ssn => "ssn",
+            PiiKind::Phone => "phone",
+            PiiKind::Unit => "unit",
+        }
+    }
+
+    /// Replacement token written in place of each match.
+    pub fn token(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "[EMAIL]",
+            PiiKind::Ssn => "[SSN]",
+            PiiKind::Phone => "[PHONE]",
+            PiiKind::Unit => "[UNIT]",
+        }
+    }
+
+    fn pattern(&self) -> &'static str {
+        match self {
+            PiiKind::Email => r"(?i)\b[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,}\b",
+            PiiKind::Ssn => r"\b\d{3}-\d{2}-\d{4}\b",
+            PiiKind::Phone => r"(?:\+?1[\s.-]?)?(?:\(\d{3}\)|\b\d{3})[\s.-]?\d{3}[\s.-]?\d{4}\b",
+            PiiKind::Unit => {
+                r"(?i)(?:\b(?:apt|apartment|unit|suite|ste|rm|room)\b\.?\s*#?|#)\s*\d+[A-Z]?\b"
+            }
+        }
+    }
+}
+
+impl fmt::Display for PiiKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PiiKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PiiKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| anyhow!("Unknown PII detector: {}", s))
+    }
+}
+
+/// Replaces PII in free text with type tokens such as `[PHONE]`.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    detectors: Vec<(PiiKind, Regex)>,
+}
+
+impl Redactor {
+    pub fn new(kinds: &[PiiKind]) -> Self {
+        let detectors = PiiKind::ALL
+            .iter()
+            .filter(|kind| kinds.contains(kind))
+            .map(|kind| {
+                let regex = Regex::new(kind.pattern()).expect("built-in PII pattern is valid");
+                (*kind, regex)
+            })
+            .collect();
+
+        Self { detectors }
+    }
+
+    /// Parses a comma-separated detector list such as `phone,email`;
+    /// `all` enables every detector and `none` (or an empty list) disables redaction.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let kinds = match spec.trim().to_lowercase().as_str() {
+            "all" => PiiKind::ALL.to_vec(),
+            "" | "none" => Vec::new(),
+            list => list
+                .split(',')
+                .filter(|k| !k.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        Ok(Self::new(&kinds))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+
+    /// Redacts `text`, calling `on_match` once per replaced match.
+    pub fn redact<'a>(&self, text: &'a str, mut on_match: impl FnMut(PiiKind)) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(text);
+
+        for (kind, regex) in &self.detectors {
+            let matches = regex.find_iter(&result).count();
+            if matches == 0 {
+                continue;
+            }
+            (0..matches).for_each(|_| on_match(*kind));
+            result = Cow::Owned(regex.replace_all(&result, kind.token()).into_owned());
+        }
+
+        result
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new(PiiKind::ALL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact_all(text: &str) -> (String, Vec<PiiKind>) {
+        let mut found = Vec::new();
+        let redacted = Redactor::default().redact(text, |kind| found.push(kind));
+        (redacted.into_owned(), found)
+    }
+
+    #[test]
+    fn test_redact_contact_details() {
+        let (text, found) =
+            redact_all("Call tenant at (718) 555-0123 or 212.555.0199, email jo.doe@example.com");
+        assert_eq!(text, "Call tenant at [PHONE] or [PHONE], email [EMAIL]");
+        assert_eq!(found, vec![PiiKind::Email, PiiKind::Phone, PiiKind::Phone]);
+    }
+
+    #[test]
+    fn test_redact_ssn_and_units() {
+        let (text, _) = redact_all("SSN 123-45-6789 given; leak in Apt. 4B and unit #12");
+        assert_eq!(text, "SSN [SSN] given; leak in [UNIT] and [UNIT]");
+    }
+
+    #[test]
+    fn test_redact_leaves_clean_text_borrowed() {
+        let redactor = Redactor::default();
+        let redacted = redactor.redact("Loud Music/Party", |_| {});
+        assert!(matches!(redacted, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_parse_detector_list() {
+        assert!(Redactor::parse("none").unwrap().is_empty());
+        assert_eq!(Redactor::parse("phone, email").unwrap().detectors.len(), 2);
+        assert!(Redactor::parse("passport").is_err());
+    }
+}
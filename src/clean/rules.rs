@@ -0,0 +1,255 @@
+// Validation rules and per-rule failure policies
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::clean::outliers::ResolutionOutliers;
+use crate::clean::validator::Validator;
+use crate::db::schema::ServiceRequest;
+use crate::etl::derive::resolution_hours;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rule {
+    UniqueKey,
+    CreatedAtRange,
+    ClosedAtRange,
+    Borough,
+    Coordinates,
+    ClosedBeforeCreated,
+    ResolutionOutlier,
+}
+
+/// State rules consult besides the record itself.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleContext<'a> {
+    pub validator: &'a Validator,
+    pub outliers: &'a ResolutionOutliers,
+}
+
+impl Rule {
+    pub const ALL: &'static [Rule] = &[
+        Rule::UniqueKey,
+        Rule::CreatedAtRange,
+        Rule::ClosedAtRange,
+        Rule::Borough,
+        Rule::Coordinates,
+        Rule::ClosedBeforeCreated,
+        Rule::ResolutionOutlier,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rule::UniqueKey => "unique_key",
+            Rule::CreatedAtRange => "created_at_range",
+            Rule::ClosedAtRange => "closed_at_range",
+            Rule::Borough => "borough",
+            Rule::Coordinates => "coordinates",
+            Rule::ClosedBeforeCreated => "closed_before_created",
+            Rule::ResolutionOutlier => "resolution_outlier",
+        }
+    }
+
+    /// Policy used when none is configured. Outliers are plausible data, so
+    /// they are flagged rather than dropped.
+    pub fn default_policy(&self) -> RulePolicy {
+        match self {
+            Rule::ResolutionOutlier => RulePolicy::Flag,
+            _ => RulePolicy::Reject,
+        }
+    }
+
+    /// Returns true when `record` satisfies the rule. Absent optional fields
+    /// always pass.
+    pub fn check(&self, context: &RuleContext, record: &ServiceRequest) -> bool {
+        let validator = context.validator;
+        match self {
+            Rule::UniqueKey => validator.is_valid_unique_key(record.unique_key),
+            Rule::CreatedAtRange => validator.validate_timestamp(record.created_at),
+            Rule::ClosedAtRange => record
+                .closed_at
+                .is_none_or(|closed| validator.validate_timestamp(closed)),
+            Rule::Borough => record
+                .borough
+                .as_deref()
+                .is_none_or(|borough| validator.validate_borough(borough)),
+            Rule::Coordinates => match (record.latitude, record.longitude) {
+                (Some(lat), Some(lon)) => validator.validate_coordinates(lat, lon),
+                _ => true,
+            },
+            Rule::ClosedBeforeCreated => record
+                .closed_at
+                .is_none_or(|closed| closed >= record.created_at),
+            Rule::ResolutionOutlier => resolution_hours(record)
+                .is_none_or(|hours| !context.outliers.is_outlier(&record.complaint_type, hours)),
+        }
+    }
+
+    /// Whether the offending field can be cleared instead of dropping the record.
+    pub fn supports_nullify(&self) -> bool {
+        !matches!(
+            self,
+            Rule::UniqueKey | Rule::CreatedAtRange | Rule::ResolutionOutlier
+        )
+    }
+
+    /// Whether a CHECK constraint on service_requests rejects the failing
+    /// value, so a flagged record must have it cleared to be loaded.
+    pub fn constrained_by_schema(&self) -> bool {
+        matches!(self, Rule::Borough)
+    }
+
+    /// Clears the field(s) that violate the rule.
+    pub fn nullify(&self, record: &mut ServiceRequest) {
+        match self {
+            Rule::UniqueKey | Rule::CreatedAtRange | Rule::ResolutionOutlier => {}
+            Rule::ClosedAtRange => record.closed_at = None,
+            Rule::Borough => record.borough = None,
+            Rule::Coordinates => {
+                record.latitude = None;
+                record.longitude = None;
+            }
+            Rule::ClosedBeforeCreated => record.closed_at = None,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Rule::ALL
+            .iter()
+            .copied()
+            .find(|rule| rule.as_str() == s.trim())
+            .ok_or_else(|| anyhow!("Unknown validation rule: {}", s))
+    }
+}
+
+/// What to do with a record that fails a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulePolicy {
+    /// Drop the whole record
+    Reject,
+    /// Clear the offending field and keep the record
+    Nullify,
+    /// Keep the record unchanged and note the failed rule on it
+    Flag,
+    /// Load the record into `service_requests_quarantine` instead
+    Quarantine,
+}
+
+impl FromStr for RulePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "reject" => Ok(RulePolicy::Reject),
+            "nullify" => Ok(RulePolicy::Nullify),
+            "flag" => Ok(RulePolicy::Flag),
+            "quarantine" => Ok(RulePolicy::Quarantine),
+            other => Err(anyhow!("Unknown rule policy: {}", other)),
+        }
+    }
+}
+
+/// Policy per rule; rules without an explicit policy use
+/// [`Rule::default_policy`].
+#[derive(Debug, Clone, Default)]
+pub struct RulePolicies {
+    policies: HashMap<Rule, RulePolicy>,
+}
+
+impl RulePolicies {
+    /// Parses a comma-separated `rule=policy` list, e.g.
+    /// `coordinates=nullify,borough=flag`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut policies = Self::default();
+
+        for pair in spec.split(',').filter(|p| !p.trim().is_empty()) {
+            let (rule, policy) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected rule=policy, got: {}", pair))?;
+            policies = policies.with(rule.parse()?, policy.parse()?)?;
+        }
+
+        Ok(policies)
+    }
+
+    pub fn with(mut self, rule: Rule, policy: RulePolicy) -> Result<Self> {
+        if policy == RulePolicy::Nullify && !rule.supports_nullify() {
+            bail!("Rule {} cannot use the nullify policy", rule);
+        }
+        self.policies.insert(rule, policy);
+        Ok(self)
+    }
+
+    pub fn policy(&self, rule: Rule) -> RulePolicy {
+        self.policies
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_policy())
+    }
+}
+
+/// Records affected by each policy outcome for a single rule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleOutcomes {
+    pub rejected: u64,
+    pub nullified: u64,
+    pub flagged: u64,
+    pub quarantined: u64,
+}
+
+impl RuleOutcomes {
+    pub fn record(&mut self, policy: RulePolicy) {
+        match policy {
+            RulePolicy::Reject => self.rejected += 1,
+            RulePolicy::Nullify => self.nullified += 1,
+            RulePolicy::Flag => self.flagged += 1,
+            RulePolicy::Quarantine => self.quarantined += 1,
+        }
+    }
+
+    pub fn add(&mut self, other: &RuleOutcomes) {
+        self.rejected += other.rejected;
+        self.nullified += other.nullified;
+        self.flagged += other.flagged;
+        self.quarantined += other.quarantined;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule_policies() {
+        let policies = RulePolicies::parse("coordinates=nullify, borough=flag").unwrap();
+        assert_eq!(policies.policy(Rule::Coordinates), RulePolicy::Nullify);
+        assert_eq!(policies.policy(Rule::Borough), RulePolicy::Flag);
+        assert_eq!(policies.policy(Rule::UniqueKey), RulePolicy::Reject);
+        assert_eq!(policies.policy(Rule::ResolutionOutlier), RulePolicy::Flag);
+
+        let policies = RulePolicies::parse("resolution_outlier=quarantine").unwrap();
+        assert_eq!(
+            policies.policy(Rule::ResolutionOutlier),
+            RulePolicy::Quarantine
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_policies_rejects_invalid_entries() {
+        assert!(RulePolicies::parse("unique_key=nullify").is_err());
+        assert!(RulePolicies::parse("borough=ignore").is_err());
+        assert!(RulePolicies::parse("latitude=flag").is_err());
+        assert!(RulePolicies::parse("borough").is_err());
+    }
+}
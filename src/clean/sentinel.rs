@@ -0,0 +1,178 @@
+// Sentinel values - placeholders such as "Unspecified" that mean NULL
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::db::schema::ServiceRequest;
+
+/// Placeholders used in 311 exports for every nullable text field.
+pub const DEFAULT_SENTINELS: &[&str] = &["N/A", "NA", "NONE", "NULL", "UNKNOWN", "UNSPECIFIED"];
+
+/// Nullable text fields sentinel handling applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SentinelField {
+    Agency,
+    Borough,
+    Descriptor,
+    ResolutionDescription,
+}
+
+impl SentinelField {
+    pub const ALL: &'static [SentinelField] = &[
+        SentinelField::Agency,
+        SentinelField::Borough,
+        SentinelField::Descriptor,
+        SentinelField::ResolutionDescription,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SentinelField::Agency => "agency",
+            SentinelField::Borough => "borough",
+            SentinelField::Descriptor => "descriptor",
+            SentinelField::ResolutionDescription => "resolution_description",
+        }
+    }
+
+    fn value_mut<'a>(&self, record: &'a mut ServiceRequest) -> &'a mut Option<String> {
+        match self {
+            SentinelField::Agency => &mut record.agency,
+            SentinelField::Borough => &mut record.borough,
+            SentinelField::Descriptor => &mut record.descriptor,
+            SentinelField::ResolutionDescription => &mut record.resolution_description,
+        }
+    }
+}
+
+impl fmt::Display for SentinelField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SentinelField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        SentinelField::ALL
+            .iter()
+            .copied()
+            .find(|field| field.as_str() == s.trim())
+            .ok_or_else(|| anyhow!("Unknown sentinel field: {}", s))
+    }
+}
+
+/// Per-field placeholder values that are mapped to NULL. Matching ignores
+/// case and surrounding whitespace; blank values are always NULL.
+#[derive(Debug, Clone)]
+pub struct Sentinels {
+    by_field: BTreeMap<SentinelField, Vec<String>>,
+}
+
+impl Sentinels {
+    /// No sentinels beyond blank values.
+    pub fn none() -> Self {
+        Self {
+            by_field: BTreeMap::new(),
+        }
+    }
+
+    /// Parses `field=value|value;field=value` overrides on top of the
+    /// defaults. An empty value list (`descriptor=`) leaves only blank values
+    /// as sentinels for that field.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut sentinels = Self::default();
+
+        for entry in spec.split(';').filter(|e| !e.trim().is_empty()) {
+            let (field, values) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected field=value|value, got: {}", entry))?;
+            let values: Vec<&str> = values.split('|').collect();
+            sentinels = sentinels.with_field(field.parse()?, &values);
+        }
+
+        Ok(sentinels)
+    }
+
+    pub fn with_field<S: AsRef<str>>(mut self, field: SentinelField, values: &[S]) -> Self {
+        let values = values
+            .iter()
+            .map(|v| v.as_ref().trim().to_uppercase())
+            .filter(|v| !v.is_empty())
+            .collect();
+        self.by_field.insert(field, values);
+        self
+    }
+
+    pub fn is_sentinel(&self, field: SentinelField, value: &str) -> bool {
+        let value = value.trim();
+        value.is_empty()
+            || self
+                .by_field
+                .get(&field)
+                .is_some_and(|values| values.iter().any(|v| v.eq_ignore_ascii_case(value)))
+    }
+
+    /// Clears sentinel values on `record`, calling `on_null` for each field cleared.
+    pub fn apply(&self, record: &mut ServiceRequest, mut on_null: impl FnMut(SentinelField)) {
+        for &field in SentinelField::ALL {
+            let value = field.value_mut(record);
+            if value.as_deref().is_some_and(|v| self.is_sentinel(field, v)) {
+                *value = None;
+                on_null(field);
+            }
+        }
+    }
+}
+
+impl Default for Sentinels {
+    fn default() -> Self {
+        SentinelField::ALL
+            .iter()
+            .fold(Self::none(), |sentinels, &field| {
+                sentinels.with_field(field, DEFAULT_SENTINELS)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_sentinels_null_placeholders() {
+        let mut record = ServiceRequest {
+            borough: Some("Unspecified".to_string()),
+            descriptor: Some("   ".to_string()),
+            agency: Some("NYPD".to_string()),
+            ..Default::default()
+        };
+
+        let mut nulled = Vec::new();
+        Sentinels::default().apply(&mut record, |field| nulled.push(field));
+
+        assert_eq!(record.borough, None);
+        assert_eq!(record.descriptor, None);
+        assert_eq!(record.agency.as_deref(), Some("NYPD"));
+        assert_eq!(
+            nulled,
+            vec![SentinelField::Borough, SentinelField::Descriptor]
+        );
+    }
+
+    #[test]
+    fn test_parse_sentinel_overrides() {
+        let sentinels = Sentinels::parse("borough=Unspecified|0; descriptor=").unwrap();
+
+        assert!(sentinels.is_sentinel(SentinelField::Borough, "0"));
+        assert!(!sentinels.is_sentinel(SentinelField::Borough, "N/A"));
+        assert!(!sentinels.is_sentinel(SentinelField::Descriptor, "N/A"));
+        assert!(sentinels.is_sentinel(SentinelField::Descriptor, ""));
+        assert!(sentinels.is_sentinel(SentinelField::Agency, "n/a"));
+
+        assert!(Sentinels::parse("latitude=0").is_err());
+        assert!(Sentinels::parse("borough").is_err());
+    }
+}
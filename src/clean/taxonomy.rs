@@ -0,0 +1,171 @@
+// Complaint-type taxonomy and canonicalization
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A single mapping from a raw complaint type (and optionally a descriptor)
+/// to a canonical category/subcategory.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaxonomyEntry {
+    pub complaint_type: String,
+    #[serde(default)]
+    pub descriptor: Option<String>,
+    pub category: String,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classification {
+    pub category: String,
+    pub subcategory: Option<String>,
+}
+
+/// Lookup table of canonical complaint categories.
+///
+/// Raw values are matched on their canonical key (see [`canonical_key`]), so
+/// "Noise - Residential", "NOISE - RESIDENTIAL" and "Noise-Residential" all
+/// resolve to the same entry. Descriptor-specific entries take precedence over
+/// entries that only name the complaint type.
+#[derive(Debug, Clone, Default)]
+pub struct Taxonomy {
+    by_type: HashMap<String, Classification>,
+    by_type_descriptor: HashMap<(String, String), Classification>,
+    complaint_types: BTreeMap<String, String>,
+}
+
+impl Taxonomy {
+    pub fn from_entries(entries: Vec<TaxonomyEntry>) -> Self {
+        let mut taxonomy = Self::default();
+
+        for entry in entries {
+            let type_key = canonical_key(&entry.complaint_type);
+            taxonomy
+                .complaint_types
+                .entry(type_key.clone())
+                .or_insert_with(|| entry.complaint_type.trim().to_string());
+            let classification = Classification {
+                category: entry.category.trim().to_string(),
+                subcategory: entry
+                    .subcategory
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty()),
+            };
+
+            match entry.descriptor.as_deref().map(canonical_key) {
+                Some(descriptor_key) if !descriptor_key.is_empty() => {
+                    taxonomy
+                        .by_type_descriptor
+                        .insert((type_key, descriptor_key), classification);
+                }
+                _ => {
+                    taxonomy.by_type.insert(type_key, classification);
+                }
+            }
+        }
+
+        taxonomy
+    }
+
+    /// Loads a taxonomy from a JSON file containing an array of entries.
+    pub fn load(path: &str) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read taxonomy file: {}", path))?;
+        let entries: Vec<TaxonomyEntry> =
+            serde_json::from_str(&content).context("Failed to parse taxonomy file")?;
+
+        Ok(Self::from_entries(entries))
+    }
+
+    pub fn classify(
+        &self,
+        complaint_type: &str,
+        descriptor: Option<&str>,
+    ) -> Option<&Classification> {
+        let type_key = canonical_key(complaint_type);
+
+        if let Some(descriptor) = descriptor {
+            let descriptor_key = canonical_key(descriptor);
+            if let Some(classification) = self
+                .by_type_descriptor
+                .get(&(type_key.clone(), descriptor_key))
+            {
+                return Some(classification);
+            }
+        }
+
+        self.by_type.get(&type_key)
+    }
+
+    /// Complaint types named by at least one entry, one per canonical key.
+    pub fn complaint_types(&self) -> Vec<&str> {
+        self.complaint_types.values().map(String::as_str).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_type.len() + self.by_type_descriptor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Reduces a raw categorical value to a comparison key: uppercase
+/// alphanumeric tokens separated by single spaces.
+pub fn canonical_key(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_uppercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_taxonomy() -> Taxonomy {
+        Taxonomy::from_entries(vec![
+            TaxonomyEntry {
+                complaint_type: "Noise - Residential".to_string(),
+                descriptor: None,
+                category: "Noise".to_string(),
+                subcategory: Some("Residential".to_string()),
+            },
+            TaxonomyEntry {
+                complaint_type: "Noise - Residential".to_string(),
+                descriptor: Some("Banging/Pounding".to_string()),
+                category: "Noise".to_string(),
+                subcategory: Some("Impact".to_string()),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_canonical_key_ignores_case_and_punctuation() {
+        assert_eq!(canonical_key("Noise - Residential"), "NOISE RESIDENTIAL");
+        assert_eq!(canonical_key("NOISE - RESIDENTIAL"), "NOISE RESIDENTIAL");
+        assert_eq!(canonical_key("Noise-Residential"), "NOISE RESIDENTIAL");
+    }
+
+    #[test]
+    fn test_classify_prefers_descriptor_entry() {
+        let taxonomy = sample_taxonomy();
+
+        let by_descriptor = taxonomy
+            .classify("noise-residential", Some("BANGING / POUNDING"))
+            .unwrap();
+        assert_eq!(by_descriptor.subcategory.as_deref(), Some("Impact"));
+
+        let by_type = taxonomy
+            .classify("NOISE - RESIDENTIAL", Some("Loud Music/Party"))
+            .unwrap();
+        assert_eq!(by_type.subcategory.as_deref(), Some("Residential"));
+
+        assert!(taxonomy.classify("Graffiti", None).is_none());
+    }
+}
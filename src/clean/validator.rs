@@ -1,7 +1,10 @@
 // Data validation and quality rules
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 
-const VALID_BOROUGHS: &[&str] = &["BRONX", "BROOKLYN", "MANHATTAN", "QUEENS", "STATEN ISLAND"];
+use crate::clean::normalize::normalize_text;
+
+pub const VALID_BOROUGHS: &[&str] = &["BRONX", "BROOKLYN", "MANHATTAN", "QUEENS", "STATEN ISLAND"];
 
 // NYC bounding box (approximate)
 const MIN_LAT: f64 = 40.4;
@@ -9,12 +12,67 @@ const MAX_LAT: f64 = 41.2;
 const MIN_LON: f64 = -74.3;
 const MAX_LON: f64 = -73.4;
 
+/// NYC 311 open data starts in 2010; anything older is a placeholder date.
+pub const DEFAULT_EARLIEST_DATE: NaiveDate = match NaiveDate::from_ymd_opt(2010, 1, 1) {
+    Some(date) => date,
+    None => panic!("invalid default earliest date"),
+};
+pub const DEFAULT_MAX_FUTURE_SKEW_HOURS: i64 = 24;
+
+/// Window a plausible request timestamp must fall in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemporalBounds {
+    pub earliest: DateTime<Utc>,
+    pub latest: DateTime<Utc>,
+}
+
+impl TemporalBounds {
+    /// Accepts timestamps from midnight UTC on `earliest` up to
+    /// `max_future_skew` past `now`, the run's start time.
+    pub fn new(earliest: NaiveDate, max_future_skew: Duration, now: DateTime<Utc>) -> Self {
+        Self {
+            earliest: earliest
+                .and_hms_opt(0, 0, 0)
+                .expect("midnight is valid")
+                .and_utc(),
+            latest: now + max_future_skew,
+        }
+    }
+
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        (self.earliest..=self.latest).contains(&timestamp)
+    }
+}
+
+impl Default for TemporalBounds {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_EARLIEST_DATE,
+            Duration::hours(DEFAULT_MAX_FUTURE_SKEW_HOURS),
+            Utc::now(),
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct Validator;
+pub struct Validator {
+    temporal_bounds: TemporalBounds,
+}
 
 impl Validator {
     pub fn new() -> Self {
-        Self
+        Self {
+            temporal_bounds: TemporalBounds::default(),
+        }
+    }
+
+    pub fn with_temporal_bounds(mut self, bounds: TemporalBounds) -> Self {
+        self.temporal_bounds = bounds;
+        self
+    }
+
+    pub fn validate_timestamp(&self, timestamp: DateTime<Utc>) -> bool {
+        self.temporal_bounds.contains(timestamp)
     }
 
     pub fn validate_borough(&self, borough: &str) -> bool {
@@ -32,7 +90,7 @@ impl Validator {
     }
 
     pub fn validate_coordinates(&self, lat: f64, lon: f64) -> bool {
-        lat >= MIN_LAT && lat <= MAX_LAT && lon >= MIN_LON && lon <= MAX_LON
+        (MIN_LAT..=MAX_LAT).contains(&lat) && (MIN_LON..=MAX_LON).contains(&lon)
     }
 
     pub fn validate_complaint_type(&self, complaint_type: &str) -> Result<String> {
@@ -44,7 +102,7 @@ impl Validator {
     }
 
     pub fn clean_text(&self, text: &str) -> String {
-        text.trim().to_string()
+        normalize_text(text)
     }
 
     pub fn is_valid_unique_key(&self, key: i64) -> bool {
@@ -80,9 +138,31 @@ mod tests {
         assert!(!validator.validate_coordinates(42.0, -73.0));
     }
 
+    #[test]
+    fn test_validate_timestamp() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let validator = Validator::new().with_temporal_bounds(TemporalBounds::new(
+            DEFAULT_EARLIEST_DATE,
+            Duration::hours(24),
+            now,
+        ));
+
+        assert!(validator.validate_timestamp(now));
+        assert!(validator.validate_timestamp(now + Duration::hours(23)));
+        assert!(!validator.validate_timestamp(now + Duration::hours(25)));
+        assert!(!validator.validate_timestamp(
+            DateTime::parse_from_rfc3339("1900-01-01T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc)
+        ));
+    }
+
     #[test]
     fn test_clean_text() {
         let validator = Validator::new();
         assert_eq!(validator.clean_text("  test  "), "test");
+        assert_eq!(validator.clean_text("two\u{00A0} words"), "two words");
     }
 }
@@ -1,8 +1,24 @@
 // Configuration management for UrbanFlux ETL
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::env;
 
+use crate::clean::{
+    Redactor, RulePolicies, Sentinels, TextNormalizer, DEFAULT_CORRECTION_THRESHOLD,
+    DEFAULT_EARLIEST_DATE, DEFAULT_MAX_FUTURE_SKEW_HOURS, DEFAULT_OUTLIER_MIN_SAMPLES,
+    DEFAULT_OUTLIER_Z_THRESHOLD,
+};
+use crate::db::{
+    LoadMode, Partitioning, RetryPolicy, RunMode, SwapThresholds, DEFAULT_MAX_CONNECTIONS,
+    DEFAULT_RETRY_BUDGET_SECS, DEFAULT_RETRY_MAX_ATTEMPTS, DEFAULT_SWAP_MIN_RATIO,
+    DEFAULT_SWAP_MIN_ROWS, DEFAULT_WATERMARK_LOOKBACK_HOURS,
+};
+use crate::etl::{
+    DerivedColumn, ErrorBudget, Expression, DEFAULT_GEOHASH_PRECISION, DEFAULT_LOADERS,
+};
+use crate::geo::{DEFAULT_HEX_CELL_METERS, MAX_GEOHASH_PRECISION};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub database: DatabaseConfig,
@@ -22,14 +38,62 @@ pub struct DatabaseConfig {
 pub struct EtlConfig {
     pub input_path: String,
     pub chunk_size: usize,
-    pub mode: String,
+    /// Threads the transform phase spreads chunks over
+    pub workers: usize,
+    /// Chunks loaded concurrently, each over its own connection
+    pub loaders: usize,
+    /// Records that may fail to load before the run is aborted
+    pub max_failed_rows: u64,
+    /// Chunks that may contain failed records before the run is aborted
+    pub max_failed_chunks: u64,
+    /// Attempts at a database operation failing with transient errors
+    pub retry_max_attempts: u32,
+    /// Seconds after which a failing database operation is no longer retried
+    pub retry_budget_secs: u64,
+    pub mode: RunMode,
+    /// Rows a full refresh must load before it may replace the live table
+    pub swap_min_rows: u64,
+    /// Smallest fraction of the live row count a full refresh may replace it with
+    pub swap_min_ratio: f64,
+    /// How far before the watermark an incremental run starts, to pick up
+    /// late-arriving records
+    pub watermark_lookback_hours: i64,
+    /// How rows already in the database are handled, see [`LoadMode`]
+    pub load_mode: LoadMode,
+    /// Layout `db init` creates service_requests with, see [`Partitioning`]
+    pub partitioning: Partitioning,
+    pub taxonomy_path: Option<String>,
+    pub correction_threshold: f64,
+    pub correction_aliases_path: Option<String>,
+    pub geohash_precision: usize,
+    pub hex_cell_meters: f64,
+    /// Comma-separated `rule=policy` list, see [`RulePolicies::parse`]
+    pub rule_policies: String,
+    /// Comma-separated PII detectors applied to free text, see [`Redactor::parse`]
+    pub pii_detectors: String,
+    /// Earliest plausible `created_at` / `closed_at` date (UTC)
+    pub earliest_date: NaiveDate,
+    /// How far past the run's start time a timestamp may lie
+    pub max_future_skew_hours: i64,
+    pub outlier_z_threshold: f64,
+    pub outlier_min_samples: u64,
+    /// `field=casing` overrides, see [`TextNormalizer::parse_casing`]
+    pub text_casing: String,
+    /// `field=length` overrides, see [`TextNormalizer::parse_max_length`]
+    pub text_max_length: String,
+    /// `field=value|value;...` overrides of the default sentinels, see [`Sentinels::parse`]
+    pub sentinels: String,
+    /// Predicate records must satisfy to be loaded, see [`Expression`]
+    pub filter: Option<String>,
+    /// `;`-separated `name = expression` list, see [`DerivedColumn::parse_list`]
+    pub derived_columns: String,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
         dotenvy::dotenv().ok();
 
-        Ok(Config {
+        let config = Config {
             database: DatabaseConfig {
                 host: env::var("PGHOST").unwrap_or_else(|_| "localhost".to_string()),
                 port: env::var("PGPORT")
@@ -47,9 +111,176 @@ impl Config {
                     .unwrap_or_else(|_| "100000".to_string())
                     .parse()
                     .context("Invalid ETL_CHUNK_SIZE")?,
-                mode: env::var("ETL_MODE").unwrap_or_else(|_| "full".to_string()),
+                workers: match env::var("ETL_WORKERS") {
+                    Ok(workers) => workers.parse().context("Invalid ETL_WORKERS")?,
+                    Err(_) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+                },
+                loaders: env::var("ETL_LOADERS")
+                    .unwrap_or_else(|_| DEFAULT_LOADERS.to_string())
+                    .parse()
+                    .context("Invalid ETL_LOADERS")?,
+                max_failed_rows: env::var("ETL_MAX_FAILED_ROWS")
+                    .unwrap_or_else(|_| "0".to_string())
+                    .parse()
+                    .context("Invalid ETL_MAX_FAILED_ROWS")?,
+                max_failed_chunks: env::var("ETL_MAX_FAILED_CHUNKS")
+                    .unwrap_or_else(|_| "0".to_string())
+                    .parse()
+                    .context("Invalid ETL_MAX_FAILED_CHUNKS")?,
+                retry_max_attempts: env::var("ETL_RETRY_MAX_ATTEMPTS")
+                    .unwrap_or_else(|_| DEFAULT_RETRY_MAX_ATTEMPTS.to_string())
+                    .parse()
+                    .context("Invalid ETL_RETRY_MAX_ATTEMPTS")?,
+                retry_budget_secs: env::var("ETL_RETRY_BUDGET_SECS")
+                    .unwrap_or_else(|_| DEFAULT_RETRY_BUDGET_SECS.to_string())
+                    .parse()
+                    .context("Invalid ETL_RETRY_BUDGET_SECS")?,
+                mode: env::var("ETL_MODE")
+                    .unwrap_or_else(|_| "full".to_string())
+                    .parse()
+                    .context("Invalid ETL_MODE")?,
+                swap_min_rows: env::var("ETL_SWAP_MIN_ROWS")
+                    .unwrap_or_else(|_| DEFAULT_SWAP_MIN_ROWS.to_string())
+                    .parse()
+                    .context("Invalid ETL_SWAP_MIN_ROWS")?,
+                swap_min_ratio: env::var("ETL_SWAP_MIN_RATIO")
+                    .unwrap_or_else(|_| DEFAULT_SWAP_MIN_RATIO.to_string())
+                    .parse()
+                    .context("Invalid ETL_SWAP_MIN_RATIO")?,
+                watermark_lookback_hours: env::var("ETL_WATERMARK_LOOKBACK_HOURS")
+                    .unwrap_or_else(|_| DEFAULT_WATERMARK_LOOKBACK_HOURS.to_string())
+                    .parse()
+                    .context("Invalid ETL_WATERMARK_LOOKBACK_HOURS")?,
+                load_mode: env::var("ETL_LOAD_MODE")
+                    .unwrap_or_else(|_| "insert".to_string())
+                    .parse()
+                    .context("Invalid ETL_LOAD_MODE")?,
+                partitioning: env::var("ETL_PARTITIONING")
+                    .unwrap_or_else(|_| "none".to_string())
+                    .parse()
+                    .context("Invalid ETL_PARTITIONING")?,
+                taxonomy_path: env::var("ETL_TAXONOMY_PATH").ok(),
+                correction_threshold: env::var("ETL_CORRECTION_THRESHOLD")
+                    .unwrap_or_else(|_| DEFAULT_CORRECTION_THRESHOLD.to_string())
+                    .parse()
+                    .context("Invalid ETL_CORRECTION_THRESHOLD")?,
+                correction_aliases_path: env::var("ETL_CORRECTION_ALIASES_PATH").ok(),
+                geohash_precision: env::var("ETL_GEOHASH_PRECISION")
+                    .unwrap_or_else(|_| DEFAULT_GEOHASH_PRECISION.to_string())
+                    .parse()
+                    .context("Invalid ETL_GEOHASH_PRECISION")?,
+                hex_cell_meters: env::var("ETL_HEX_CELL_METERS")
+                    .unwrap_or_else(|_| DEFAULT_HEX_CELL_METERS.to_string())
+                    .parse()
+                    .context("Invalid ETL_HEX_CELL_METERS")?,
+                rule_policies: env::var("ETL_RULE_POLICIES").unwrap_or_default(),
+                pii_detectors: env::var("ETL_PII_DETECTORS").unwrap_or_else(|_| "all".to_string()),
+                earliest_date: env::var("ETL_EARLIEST_DATE")
+                    .unwrap_or_else(|_| DEFAULT_EARLIEST_DATE.to_string())
+                    .parse()
+                    .context("Invalid ETL_EARLIEST_DATE")?,
+                max_future_skew_hours: env::var("ETL_MAX_FUTURE_SKEW_HOURS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_FUTURE_SKEW_HOURS.to_string())
+                    .parse()
+                    .context("Invalid ETL_MAX_FUTURE_SKEW_HOURS")?,
+                outlier_z_threshold: env::var("ETL_OUTLIER_Z_THRESHOLD")
+                    .unwrap_or_else(|_| DEFAULT_OUTLIER_Z_THRESHOLD.to_string())
+                    .parse()
+                    .context("Invalid ETL_OUTLIER_Z_THRESHOLD")?,
+                outlier_min_samples: env::var("ETL_OUTLIER_MIN_SAMPLES")
+                    .unwrap_or_else(|_| DEFAULT_OUTLIER_MIN_SAMPLES.to_string())
+                    .parse()
+                    .context("Invalid ETL_OUTLIER_MIN_SAMPLES")?,
+                text_casing: env::var("ETL_TEXT_CASING").unwrap_or_default(),
+                text_max_length: env::var("ETL_TEXT_MAX_LENGTH").unwrap_or_default(),
+                sentinels: env::var("ETL_SENTINELS").unwrap_or_default(),
+                filter: env::var("ETL_FILTER").ok(),
+                derived_columns: env::var("ETL_DERIVE").unwrap_or_default(),
             },
-        })
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            (1..=MAX_GEOHASH_PRECISION).contains(&self.etl.geohash_precision),
+            "ETL_GEOHASH_PRECISION must be between 1 and {}",
+            MAX_GEOHASH_PRECISION
+        );
+        ensure!(self.etl.workers > 0, "ETL_WORKERS must be at least 1");
+        ensure!(self.etl.loaders > 0, "ETL_LOADERS must be at least 1");
+        ensure!(
+            self.etl.retry_max_attempts > 0,
+            "ETL_RETRY_MAX_ATTEMPTS must be at least 1"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&self.etl.swap_min_ratio),
+            "ETL_SWAP_MIN_RATIO must be between 0 and 1"
+        );
+        ensure!(
+            self.etl.watermark_lookback_hours >= 0,
+            "ETL_WATERMARK_LOOKBACK_HOURS must not be negative"
+        );
+        ensure!(
+            self.etl.hex_cell_meters > 0.0,
+            "ETL_HEX_CELL_METERS must be positive"
+        );
+        ensure!(
+            self.etl.max_future_skew_hours >= 0,
+            "ETL_MAX_FUTURE_SKEW_HOURS must not be negative"
+        );
+        ensure!(
+            self.etl.outlier_z_threshold > 0.0,
+            "ETL_OUTLIER_Z_THRESHOLD must be positive"
+        );
+        RulePolicies::parse(&self.etl.rule_policies).context("Invalid ETL_RULE_POLICIES")?;
+        Redactor::parse(&self.etl.pii_detectors).context("Invalid ETL_PII_DETECTORS")?;
+        self.text_normalizer()?;
+        Sentinels::parse(&self.etl.sentinels).context("Invalid ETL_SENTINELS")?;
+        if let Some(ref filter) = self.etl.filter {
+            Expression::compile_predicate(filter).context("Invalid ETL_FILTER")?;
+        }
+        DerivedColumn::parse_list(&self.etl.derived_columns).context("Invalid ETL_DERIVE")?;
+        Ok(())
+    }
+
+    pub fn error_budget(&self) -> ErrorBudget {
+        ErrorBudget {
+            max_failed_rows: self.etl.max_failed_rows,
+            max_failed_chunks: self.etl.max_failed_chunks,
+        }
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.etl.retry_max_attempts,
+            budget: std::time::Duration::from_secs(self.etl.retry_budget_secs),
+            ..Default::default()
+        }
+    }
+
+    /// Connections the pool may open: one per loader, plus room for run
+    /// bookkeeping alongside them.
+    pub fn max_connections(&self) -> u32 {
+        (self.etl.loaders as u32 + 2).max(DEFAULT_MAX_CONNECTIONS)
+    }
+
+    pub fn swap_thresholds(&self) -> SwapThresholds {
+        SwapThresholds {
+            min_rows: self.etl.swap_min_rows,
+            min_ratio: self.etl.swap_min_ratio,
+        }
+    }
+
+    /// Default text normalizer with the configured overrides applied.
+    pub fn text_normalizer(&self) -> Result<TextNormalizer> {
+        TextNormalizer::default()
+            .parse_casing(&self.etl.text_casing)
+            .context("Invalid ETL_TEXT_CASING")?
+            .parse_max_length(&self.etl.text_max_length)
+            .context("Invalid ETL_TEXT_MAX_LENGTH")
     }
 
     pub fn database_url(&self) -> String {
@@ -0,0 +1,189 @@
+// COPY FROM STDIN payloads in PostgreSQL's text format
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Marker COPY's text format reads as NULL.
+const NULL: &str = "\\N";
+
+/// Builds rows for `COPY ... FROM STDIN` in text format.
+///
+/// Values are written column by column and rows closed with
+/// [`end_row`](Self::end_row); backslashes, tabs and line breaks inside
+/// values are escaped so they cannot split a field or row.
+#[derive(Debug, Default)]
+pub struct CopyWriter {
+    buffer: String,
+    row_started: bool,
+}
+
+impl CopyWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Hands over the rows written so far and starts a new buffer.
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer).into_bytes()
+    }
+
+    pub fn end_row(&mut self) {
+        self.buffer.push('\n');
+        self.row_started = false;
+    }
+
+    pub fn text(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(value) => {
+                self.start_field();
+                escape_into(&mut self.buffer, value);
+            }
+            None => self.null(),
+        }
+        self
+    }
+
+    /// Integers and other values whose `Display` form needs no escaping.
+    pub fn plain(&mut self, value: Option<impl Display>) -> &mut Self {
+        match value {
+            Some(value) => self.raw(&value.to_string()),
+            None => self.null(),
+        }
+        self
+    }
+
+    pub fn float(&mut self, value: Option<f64>) -> &mut Self {
+        match value {
+            Some(value) if value.is_nan() => self.raw("NaN"),
+            Some(value) if value.is_infinite() => {
+                self.raw(if value > 0.0 { "Infinity" } else { "-Infinity" })
+            }
+            Some(value) => self.raw(&value.to_string()),
+            None => self.null(),
+        }
+        self
+    }
+
+    pub fn bool(&mut self, value: Option<bool>) -> &mut Self {
+        match value {
+            Some(value) => self.raw(if value { "t" } else { "f" }),
+            None => self.null(),
+        }
+        self
+    }
+
+    pub fn timestamp(&mut self, value: Option<DateTime<Utc>>) -> &mut Self {
+        match value {
+            Some(value) => self.raw(&value.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            None => self.null(),
+        }
+        self
+    }
+
+    pub fn date(&mut self, value: Option<NaiveDate>) -> &mut Self {
+        self.plain(value)
+    }
+
+    /// A `TEXT[]` value; every element is quoted so commas and braces are kept.
+    pub fn text_array(&mut self, values: &[String]) -> &mut Self {
+        let mut literal = String::from("{");
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                literal.push(',');
+            }
+            literal.push('"');
+            for c in value.chars() {
+                if c == '"' || c == '\\' {
+                    literal.push('\\');
+                }
+                literal.push(c);
+            }
+            literal.push('"');
+        }
+        literal.push('}');
+        self.text(Some(&literal))
+    }
+
+    pub fn json(&mut self, value: &impl Serialize) -> &mut Self {
+        let json = serde_json::to_string(value).expect("value serializes to JSON");
+        self.text(Some(&json))
+    }
+
+    fn null(&mut self) {
+        self.raw(NULL);
+    }
+
+    fn raw(&mut self, value: &str) {
+        self.start_field();
+        self.buffer.push_str(value);
+    }
+
+    fn start_field(&mut self) {
+        if self.row_started {
+            self.buffer.push('\t');
+        }
+        self.row_started = true;
+    }
+}
+
+fn escape_into(buffer: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => buffer.push_str("\\\\"),
+            '\t' => buffer.push_str("\\t"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            c => buffer.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_copy_escaping_and_nulls() {
+        let mut writer = CopyWriter::new();
+        writer
+            .plain(Some(42))
+            .text(Some("tab\there\nC:\\dir"))
+            .text(None)
+            .float(Some(f64::NAN))
+            .float(Some(-1.5))
+            .bool(Some(true))
+            .timestamp(Some(Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap()))
+            .date(NaiveDate::from_ymd_opt(2025, 1, 2));
+        writer.end_row();
+
+        assert_eq!(
+            String::from_utf8(writer.take()).unwrap(),
+            "42\ttab\\there\\nC:\\\\dir\t\\N\tNaN\t-1.5\tt\t2025-01-02T03:04:05Z\t2025-01-02\n"
+        );
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn test_copy_arrays_and_json() {
+        let mut writer = CopyWriter::new();
+        writer
+            .text_array(&[])
+            .text_array(&["a,b".to_string(), "say \"hi\"".to_string()])
+            .json(&serde_json::json!({"note": "line\nbreak"}));
+        writer.end_row();
+
+        assert_eq!(
+            String::from_utf8(writer.take()).unwrap(),
+            "{}\t{\"a,b\",\"say \\\\\"hi\\\\\"\"}\t{\"note\":\"line\\\\nbreak\"}\n"
+        );
+    }
+}
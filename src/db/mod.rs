@@ -1,5 +1,15 @@
 // Database module - Schema, migrations, and queries
+pub mod copy;
+pub mod partition;
+pub mod refresh;
+pub mod retry;
 pub mod schema;
+pub mod watermark;
 
 // Re-exports
+pub use copy::*;
+pub use partition::*;
+pub use refresh::*;
+pub use retry::*;
 pub use schema::*;
+pub use watermark::*;
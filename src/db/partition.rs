@@ -0,0 +1,315 @@
+// Monthly partitioning - range partitions of service_requests by created_at
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::db::schema::{Database, SERVICE_REQUESTS_TABLE};
+
+/// Months past the current one `db maintain-partitions` creates by default.
+pub const DEFAULT_PARTITIONS_AHEAD: u32 = 3;
+/// Appended to a detached partition's name, so the month's partition can be
+/// created again.
+const DETACHED_SUFFIX: &str = "_detached";
+
+/// Layout `db init` creates service_requests with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Partitioning {
+    /// One ordinary table
+    #[default]
+    None,
+    /// Range-partitioned on `created_at`, one partition per UTC month
+    Monthly,
+}
+
+impl FromStr for Partitioning {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "none" => Ok(Partitioning::None),
+            "monthly" => Ok(Partitioning::Monthly),
+            other => Err(anyhow!("Unknown partitioning: {}", other)),
+        }
+    }
+}
+
+/// Calendar month (UTC) of `created_at` held by one partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PartitionMonth {
+    year: i32,
+    /// 1-based
+    month: u32,
+}
+
+impl PartitionMonth {
+    pub fn of(timestamp: DateTime<Utc>) -> Self {
+        Self {
+            year: timestamp.year(),
+            month: timestamp.month(),
+        }
+    }
+
+    /// The month `months` after this one, or before it if negative.
+    pub fn add_months(&self, months: i32) -> Self {
+        let index = self.year * 12 + self.month as i32 - 1 + months;
+        Self {
+            year: index.div_euclid(12),
+            month: index.rem_euclid(12) as u32 + 1,
+        }
+    }
+
+    /// Partition of `table` holding this month, e.g. `service_requests_y2025m02`.
+    pub fn partition_name(&self, table: &str) -> String {
+        format!("{}_y{:04}m{:02}", table, self.year, self.month)
+    }
+
+    /// Month of a partition named by [`partition_name`](Self::partition_name);
+    /// `None` for partitions named otherwise.
+    pub fn from_partition_name(table: &str, name: &str) -> Option<Self> {
+        let rest = name.strip_prefix(table)?.strip_prefix("_y")?;
+        let (year, month) = rest.split_once('m')?;
+        if year.len() != 4 || month.len() != 2 {
+            return None;
+        }
+        let month = Self {
+            year: year.parse().ok()?,
+            month: month.parse().ok()?,
+        };
+        (1..=12).contains(&month.month).then_some(month)
+    }
+
+    /// `CREATE TABLE` statement for this month's partition of `table`.
+    fn create_sql(&self, table: &str) -> String {
+        let next = self.add_months(1);
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} \
+             FOR VALUES FROM ('{:04}-{:02}-01 00:00:00+00') TO ('{:04}-{:02}-01 00:00:00+00')",
+            self.partition_name(table),
+            table,
+            self.year,
+            self.month,
+            next.year,
+            next.month
+        )
+    }
+}
+
+/// Partitions `db maintain-partitions` created and detached.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartitionMaintenance {
+    pub created: Vec<String>,
+    pub detached: Vec<String>,
+}
+
+/// Months to create so that `current` and the `ahead` months after it have
+/// partitions, and existing months to detach because they end more than
+/// `retain` months before `current`.
+fn maintenance_plan(
+    existing: &BTreeSet<PartitionMonth>,
+    current: PartitionMonth,
+    ahead: u32,
+    retain: Option<u32>,
+) -> (Vec<PartitionMonth>, Vec<PartitionMonth>) {
+    let create = (0..=ahead as i32)
+        .map(|months| current.add_months(months))
+        .filter(|month| !existing.contains(month))
+        .collect();
+    let detach = match retain {
+        Some(retain) => {
+            let oldest_kept = current.add_months(-(retain as i32));
+            existing.range(..oldest_kept).copied().collect()
+        }
+        None => Vec::new(),
+    };
+    (create, detach)
+}
+
+impl Database {
+    /// Months `table` has partitions for, or `None` if it is not a
+    /// partitioned table. Partitions not named by
+    /// [`PartitionMonth::partition_name`] are left out.
+    pub async fn partition_months(&self, table: &str) -> Result<Option<BTreeSet<PartitionMonth>>> {
+        let partitioned: Option<(i32,)> = self
+            .retry_policy()
+            .run("read partitioning", || async {
+                Ok(sqlx::query_as(
+                    "SELECT 1 FROM pg_partitioned_table WHERE partrelid = $1::regclass",
+                )
+                .bind(table)
+                .fetch_optional(self.pool())
+                .await?)
+            })
+            .await
+            .with_context(|| format!("Failed to read partitioning of {table}"))?;
+        if partitioned.is_none() {
+            return Ok(None);
+        }
+
+        let partitions: Vec<(String,)> = self
+            .retry_policy()
+            .run("read partitions", || async {
+                Ok(sqlx::query_as(
+                    "SELECT c.relname::text FROM pg_inherits i \
+                     JOIN pg_class c ON c.oid = i.inhrelid \
+                     WHERE i.inhparent = $1::regclass",
+                )
+                .bind(table)
+                .fetch_all(self.pool())
+                .await?)
+            })
+            .await
+            .with_context(|| format!("Failed to read partitions of {table}"))?;
+
+        Ok(Some(
+            partitions
+                .iter()
+                .filter_map(|(name,)| PartitionMonth::from_partition_name(table, name))
+                .collect(),
+        ))
+    }
+
+    /// Creates the partitions of `table` for `months` that do not exist yet.
+    pub async fn create_partitions(&self, table: &str, months: &[PartitionMonth]) -> Result<()> {
+        for month in months {
+            let name = month.partition_name(table);
+            self.execute_retried("create partition", &month.create_sql(table))
+                .await
+                .with_context(|| format!("Failed to create partition {name}"))?;
+            info!(partition = %name, "Created partition");
+        }
+        Ok(())
+    }
+
+    /// Creates the partitions of service_requests for the month of `now`
+    /// and the `ahead` months after it, and detaches those ending more than
+    /// `retain` months before it. Detached partitions are kept as ordinary
+    /// tables, renamed with a `_detached` suffix.
+    pub async fn maintain_partitions(
+        &self,
+        now: DateTime<Utc>,
+        ahead: u32,
+        retain: Option<u32>,
+    ) -> Result<PartitionMaintenance> {
+        let Some(existing) = self.partition_months(SERVICE_REQUESTS_TABLE).await? else {
+            bail!(
+                "{} is not partitioned; create it with ETL_PARTITIONING=monthly",
+                SERVICE_REQUESTS_TABLE
+            );
+        };
+
+        let (create, detach) = maintenance_plan(&existing, PartitionMonth::of(now), ahead, retain);
+        self.create_partitions(SERVICE_REQUESTS_TABLE, &create)
+            .await?;
+
+        let mut maintenance = PartitionMaintenance {
+            created: create
+                .iter()
+                .map(|month| month.partition_name(SERVICE_REQUESTS_TABLE))
+                .collect(),
+            detached: Vec::with_capacity(detach.len()),
+        };
+        for month in detach {
+            let name = month.partition_name(SERVICE_REQUESTS_TABLE);
+            let detached = format!("{name}{DETACHED_SUFFIX}");
+            self.retry_policy()
+                .run("detach partition", || async {
+                    let mut tx = self.pool().begin().await?;
+                    for statement in [
+                        format!("ALTER TABLE {SERVICE_REQUESTS_TABLE} DETACH PARTITION {name}"),
+                        format!("ALTER TABLE {name} RENAME TO {detached}"),
+                    ] {
+                        sqlx::query(&statement).execute(&mut *tx).await?;
+                    }
+                    tx.commit().await?;
+                    Ok(())
+                })
+                .await
+                .with_context(|| format!("Failed to detach partition {name}"))?;
+            info!(partition = %name, table = %detached, "Detached partition");
+            maintenance.detached.push(name);
+        }
+
+        Ok(maintenance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn month(year: i32, month: u32) -> PartitionMonth {
+        PartitionMonth { year, month }
+    }
+
+    #[test]
+    fn test_partition_month_arithmetic_and_names() {
+        let at = Utc.with_ymd_and_hms(2025, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(PartitionMonth::of(at), month(2025, 12));
+        assert_eq!(month(2025, 12).add_months(1), month(2026, 1));
+        assert_eq!(month(2025, 1).add_months(-13), month(2023, 12));
+
+        let name = month(2025, 2).partition_name("service_requests");
+        assert_eq!(name, "service_requests_y2025m02");
+        assert_eq!(
+            PartitionMonth::from_partition_name("service_requests", &name),
+            Some(month(2025, 2))
+        );
+        assert_eq!(
+            PartitionMonth::from_partition_name(
+                "service_requests",
+                "service_requests_shadow_y2025m02"
+            ),
+            None
+        );
+        assert_eq!(
+            PartitionMonth::from_partition_name("service_requests", "service_requests_y2025m13"),
+            None
+        );
+
+        assert_eq!(
+            month(2025, 12).create_sql("service_requests"),
+            "CREATE TABLE IF NOT EXISTS service_requests_y2025m12 PARTITION OF service_requests \
+             FOR VALUES FROM ('2025-12-01 00:00:00+00') TO ('2026-01-01 00:00:00+00')"
+        );
+    }
+
+    #[test]
+    fn test_maintenance_plan() {
+        let existing: BTreeSet<_> = [
+            month(2024, 11),
+            month(2025, 1),
+            month(2025, 2),
+            month(2025, 3),
+        ]
+        .into_iter()
+        .collect();
+
+        let (create, detach) = maintenance_plan(&existing, month(2025, 3), 2, None);
+        assert_eq!(create, vec![month(2025, 4), month(2025, 5)]);
+        assert!(detach.is_empty());
+
+        let (_, detach) = maintenance_plan(&existing, month(2025, 3), 0, Some(2));
+        assert_eq!(detach, vec![month(2024, 11)]);
+
+        let (_, detach) = maintenance_plan(&existing, month(2025, 3), 0, Some(0));
+        assert_eq!(
+            detach,
+            vec![month(2024, 11), month(2025, 1), month(2025, 2)]
+        );
+    }
+
+    #[test]
+    fn test_partitioning_from_str() {
+        assert_eq!(
+            "monthly".parse::<Partitioning>().unwrap(),
+            Partitioning::Monthly
+        );
+        assert_eq!("NONE".parse::<Partitioning>().unwrap(), Partitioning::None);
+        assert!("weekly".parse::<Partitioning>().is_err());
+    }
+}
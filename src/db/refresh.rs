@@ -0,0 +1,443 @@
+// Full refresh - load into a shadow table and swap it in atomically
+use anyhow::{ensure, Context, Result};
+use regex::Regex;
+use sqlx::{Postgres, Transaction};
+use tracing::info;
+
+use crate::db::schema::{Database, HISTORY_COLUMNS, SERVICE_REQUESTS_TABLE};
+
+/// Table a full refresh loads into before it replaces service_requests.
+pub const SHADOW_TABLE: &str = "service_requests_shadow";
+/// Suffix shadow indexes carry until the swap gives them their final names.
+const SHADOW_INDEX_SUFFIX: &str = "_shadow";
+
+pub const DEFAULT_SWAP_MIN_ROWS: u64 = 1;
+pub const DEFAULT_SWAP_MIN_RATIO: f64 = 0.9;
+
+/// Row counts a shadow table must reach before it may replace the live table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapThresholds {
+    pub min_rows: u64,
+    /// Minimum shadow / live row ratio; not checked while the live table is empty
+    pub min_ratio: f64,
+}
+
+impl Default for SwapThresholds {
+    fn default() -> Self {
+        Self {
+            min_rows: DEFAULT_SWAP_MIN_ROWS,
+            min_ratio: DEFAULT_SWAP_MIN_RATIO,
+        }
+    }
+}
+
+impl SwapThresholds {
+    pub fn check(&self, shadow_rows: u64, live_rows: u64) -> Result<()> {
+        ensure!(
+            shadow_rows >= self.min_rows,
+            "Full refresh loaded {} rows, fewer than the minimum of {}",
+            shadow_rows,
+            self.min_rows
+        );
+        if live_rows > 0 {
+            let ratio = shadow_rows as f64 / live_rows as f64;
+            ensure!(
+                ratio >= self.min_ratio,
+                "Full refresh loaded {} rows, {:.1}% of the {} live rows (minimum {:.1}%)",
+                shadow_rows,
+                ratio * 100.0,
+                live_rows,
+                self.min_ratio * 100.0
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Row counts of the table that was replaced and the one that replaced it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwapStats {
+    pub previous_rows: u64,
+    pub rows: u64,
+}
+
+/// View or materialized view that reads from service_requests and so has to
+/// be recreated when the table is replaced.
+#[derive(Debug)]
+struct DependentView {
+    name: String,
+    materialized: bool,
+    definition: String,
+    indexes: Vec<String>,
+    grants: Vec<(String, String)>,
+}
+
+impl DependentView {
+    fn kind(&self) -> &'static str {
+        if self.materialized {
+            "MATERIALIZED VIEW"
+        } else {
+            "VIEW"
+        }
+    }
+}
+
+impl Database {
+    /// Creates an empty shadow copy of service_requests with its columns,
+    /// defaults and constraints but only the primary key index, replacing
+    /// one left behind by an earlier refresh. If service_requests is
+    /// partitioned, so is the shadow, though without partitions yet.
+    pub async fn create_shadow_table(&self) -> Result<()> {
+        self.drop_shadow_table().await?;
+
+        let partitioned = self
+            .partition_months(SERVICE_REQUESTS_TABLE)
+            .await?
+            .is_some();
+        let (primary_key, partition_by) = if partitioned {
+            ("unique_key, created_at", " PARTITION BY RANGE (created_at)")
+        } else {
+            ("unique_key", "")
+        };
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {SHADOW_TABLE} (LIKE {SERVICE_REQUESTS_TABLE} INCLUDING ALL EXCLUDING INDEXES, PRIMARY KEY ({primary_key})){partition_by}"
+        );
+        self.execute_retried("create shadow table", &sql)
+            .await
+            .context("Failed to create shadow table")?;
+
+        info!(table = SHADOW_TABLE, "Created shadow table");
+        Ok(())
+    }
+
+    pub async fn drop_shadow_table(&self) -> Result<()> {
+        self.execute_retried(
+            "drop shadow table",
+            &format!("DROP TABLE IF EXISTS {SHADOW_TABLE}"),
+        )
+        .await
+        .context("Failed to drop shadow table")
+    }
+
+    /// Replaces service_requests with the loaded shadow table.
+    ///
+    /// Row counts are checked and the live table's secondary indexes built
+    /// on the shadow first; the swap itself, including history versions,
+    /// grants and dependent views, is one transaction, so readers see either
+    /// the old dataset or the new one. On error the live table is untouched,
+    /// and a transient error retries the step that hit it.
+    pub async fn swap_in_shadow_table(&self, thresholds: &SwapThresholds) -> Result<SwapStats> {
+        let stats = SwapStats {
+            previous_rows: self.count_rows(SERVICE_REQUESTS_TABLE).await?,
+            rows: self.count_rows(SHADOW_TABLE).await?,
+        };
+        thresholds.check(stats.rows, stats.previous_rows)?;
+
+        let indexes: Vec<(String, String)> = self
+            .retry_policy()
+            .run("read indexes", || async {
+                Ok(sqlx::query_as(
+                    "SELECT i.relname::text, pg_get_indexdef(i.oid) FROM pg_index x \
+                     JOIN pg_class i ON i.oid = x.indexrelid \
+                     WHERE x.indrelid = $1::regclass AND NOT x.indisprimary ORDER BY i.relname",
+                )
+                .bind(SERVICE_REQUESTS_TABLE)
+                .fetch_all(self.pool())
+                .await?)
+            })
+            .await
+            .context("Failed to read service_requests indexes")?;
+
+        for (name, definition) in &indexes {
+            self.execute_retried("build index", &shadow_index_definition(name, definition)?)
+                .await
+                .with_context(|| format!("Failed to build index {name} on shadow table"))?;
+        }
+        self.execute_retried("analyze", &format!("ANALYZE {SHADOW_TABLE}"))
+            .await
+            .context("Failed to analyze shadow table")?;
+
+        self.retry_policy()
+            .run("swap tables", || self.swap_tables(&indexes))
+            .await?;
+
+        info!(
+            previous_rows = stats.previous_rows,
+            rows = stats.rows,
+            "Swapped shadow table into service_requests"
+        );
+        Ok(stats)
+    }
+
+    /// The swap transaction: replaces service_requests with the indexed
+    /// shadow table and recreates what depended on it.
+    async fn swap_tables(&self, indexes: &[(String, String)]) -> Result<()> {
+        let mut tx = self.pool().begin().await?;
+        sqlx::query(&format!(
+            "LOCK TABLE {SERVICE_REQUESTS_TABLE} IN ACCESS EXCLUSIVE MODE"
+        ))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to lock service_requests")?;
+
+        let views = dependent_views(&mut tx).await?;
+        let grants = relation_grants(&mut tx, SERVICE_REQUESTS_TABLE).await?;
+
+        for view in views.iter().rev() {
+            sqlx::query(&format!("DROP {} {}", view.kind(), view.name))
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to drop {}", view.name))?;
+        }
+
+        sync_history(&mut tx).await?;
+
+        // Partitions are renamed after their new parent once the old
+        // table, and the partitions it is dropped with, are gone
+        let partitions: Vec<(String,)> = sqlx::query_as(
+            "SELECT c.relname::text FROM pg_inherits i \
+             JOIN pg_class c ON c.oid = i.inhrelid \
+             WHERE i.inhparent = $1::regclass ORDER BY 1",
+        )
+        .bind(SHADOW_TABLE)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to read shadow table partitions")?;
+
+        for statement in [
+            format!("DROP TABLE {SERVICE_REQUESTS_TABLE}"),
+            format!("ALTER TABLE {SHADOW_TABLE} RENAME TO {SERVICE_REQUESTS_TABLE}"),
+            format!(
+                "ALTER TABLE {SERVICE_REQUESTS_TABLE} RENAME CONSTRAINT {SHADOW_TABLE}_pkey TO {SERVICE_REQUESTS_TABLE}_pkey"
+            ),
+        ] {
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to swap tables: {statement}"))?;
+        }
+        for (partition,) in &partitions {
+            if let Some(month) = partition.strip_prefix(SHADOW_TABLE) {
+                sqlx::query(&format!(
+                    "ALTER TABLE {partition} RENAME TO {SERVICE_REQUESTS_TABLE}{month}"
+                ))
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to rename partition {partition}"))?;
+            }
+        }
+        for (name, _) in indexes {
+            sqlx::query(&format!(
+                "ALTER INDEX {name}{SHADOW_INDEX_SUFFIX} RENAME TO {name}"
+            ))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to rename index {name}"))?;
+        }
+        grant(&mut tx, SERVICE_REQUESTS_TABLE, &grants).await?;
+
+        for view in &views {
+            sqlx::query(&format!(
+                "CREATE {} {} AS {}",
+                view.kind(),
+                view.name,
+                view.definition
+            ))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to recreate {}", view.name))?;
+            for index in &view.indexes {
+                sqlx::query(index)
+                    .execute(&mut *tx)
+                    .await
+                    .with_context(|| format!("Failed to recreate index on {}", view.name))?;
+            }
+            grant(&mut tx, &view.name, &view.grants).await?;
+        }
+
+        tx.commit().await.context("Failed to commit table swap")?;
+        Ok(())
+    }
+
+    async fn count_rows(&self, table: &str) -> Result<u64> {
+        let sql = format!("SELECT COUNT(*) FROM {table}");
+        let (count,): (i64,) = self
+            .retry_policy()
+            .run("count rows", || async {
+                Ok(sqlx::query_as(&sql).fetch_one(self.pool()).await?)
+            })
+            .await
+            .with_context(|| format!("Failed to count rows in {table}"))?;
+        Ok(count as u64)
+    }
+
+    pub(crate) async fn execute_retried(&self, operation: &str, sql: &str) -> Result<()> {
+        self.retry_policy()
+            .run(operation, || async {
+                sqlx::query(sql).execute(self.pool()).await?;
+                Ok(())
+            })
+            .await
+    }
+}
+
+/// Rewrites a live-table index definition to build the same index, under a
+/// temporary name, on the shadow table; it is skipped if already built.
+/// `ONLY` is dropped, so an index of a partitioned table is built on every
+/// partition of the shadow.
+fn shadow_index_definition(name: &str, definition: &str) -> Result<String> {
+    let pattern = Regex::new(&format!(
+        r"^(CREATE (?:UNIQUE )?INDEX ){name} ON (?:ONLY )?((?:\w+\.)?){SERVICE_REQUESTS_TABLE} "
+    ))
+    .expect("index definition pattern is valid");
+    ensure!(
+        pattern.is_match(definition),
+        "Unexpected definition for index {}: {}",
+        name,
+        definition
+    );
+    Ok(pattern
+        .replace(
+            definition,
+            format!("${{1}}IF NOT EXISTS {name}{SHADOW_INDEX_SUFFIX} ON ${{2}}{SHADOW_TABLE} "),
+        )
+        .into_owned())
+}
+
+async fn dependent_views(tx: &mut Transaction<'_, Postgres>) -> Result<Vec<DependentView>> {
+    let rows: Vec<(i64, String, bool, String)> = sqlx::query_as(
+        "SELECT DISTINCT v.oid::bigint, v.relname::text, v.relkind = 'm', pg_get_viewdef(v.oid) \
+         FROM pg_depend d \
+         JOIN pg_rewrite r ON r.oid = d.objid \
+         JOIN pg_class v ON v.oid = r.ev_class \
+         WHERE d.classid = 'pg_rewrite'::regclass \
+           AND d.refobjid = $1::regclass AND v.oid <> d.refobjid \
+         ORDER BY 1",
+    )
+    .bind(SERVICE_REQUESTS_TABLE)
+    .fetch_all(&mut **tx)
+    .await
+    .context("Failed to find views over service_requests")?;
+
+    let mut views = Vec::with_capacity(rows.len());
+    for (oid, name, materialized, definition) in rows {
+        let indexes: Vec<(String,)> = sqlx::query_as(
+            "SELECT pg_get_indexdef(indexrelid) FROM pg_index WHERE indrelid = $1::bigint::oid",
+        )
+        .bind(oid)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to read indexes of {name}"))?;
+        let grants = relation_grants(tx, &name).await?;
+
+        views.push(DependentView {
+            name,
+            materialized,
+            definition: definition.trim_end().trim_end_matches(';').to_string(),
+            indexes: indexes.into_iter().map(|(index,)| index).collect(),
+            grants,
+        });
+    }
+
+    Ok(views)
+}
+
+/// Privileges granted on `relation` to roles other than its owner, as
+/// (grantee, privilege) pairs ready to be granted again.
+async fn relation_grants(
+    tx: &mut Transaction<'_, Postgres>,
+    relation: &str,
+) -> Result<Vec<(String, String)>> {
+    sqlx::query_as(
+        "SELECT CASE WHEN a.grantee = 0 THEN 'PUBLIC' ELSE quote_ident(pg_get_userbyid(a.grantee)) END, \
+                a.privilege_type \
+         FROM pg_class c, aclexplode(c.relacl) a \
+         WHERE c.oid = $1::regclass AND a.grantee <> c.relowner",
+    )
+    .bind(relation)
+    .fetch_all(&mut **tx)
+    .await
+    .with_context(|| format!("Failed to read privileges on {relation}"))
+}
+
+async fn grant(
+    tx: &mut Transaction<'_, Postgres>,
+    relation: &str,
+    grants: &[(String, String)],
+) -> Result<()> {
+    for (grantee, privilege) in grants {
+        sqlx::query(&format!("GRANT {privilege} ON {relation} TO {grantee}"))
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("Failed to grant {privilege} on {relation} to {grantee}"))?;
+    }
+    Ok(())
+}
+
+/// Brings service_request_history in line with the shadow table: current
+/// versions whose request changed or disappeared are closed, and a version
+/// is opened for every request without a current one.
+async fn sync_history(tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+    sqlx::query(&format!(
+        "UPDATE service_request_history h SET valid_to = now() \
+         WHERE h.valid_to IS NULL AND NOT EXISTS ( \
+             SELECT 1 FROM {SHADOW_TABLE} s \
+             WHERE s.unique_key = h.unique_key AND s.row_hash IS NOT DISTINCT FROM h.row_hash)"
+    ))
+    .execute(&mut **tx)
+    .await
+    .context("Failed to close history versions")?;
+
+    let columns = HISTORY_COLUMNS.join(", ");
+    sqlx::query(&format!(
+        "INSERT INTO service_request_history (unique_key, valid_from, {columns}) \
+         SELECT unique_key, now(), {columns} FROM {SHADOW_TABLE} s \
+         WHERE NOT EXISTS ( \
+             SELECT 1 FROM service_request_history h \
+             WHERE h.unique_key = s.unique_key AND h.valid_to IS NULL)"
+    ))
+    .execute(&mut **tx)
+    .await
+    .context("Failed to record history versions")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_thresholds() {
+        let thresholds = SwapThresholds::default();
+        assert!(thresholds.check(0, 0).is_err());
+        assert!(thresholds.check(5, 0).is_ok());
+        assert!(thresholds.check(95, 100).is_ok());
+        assert!(thresholds.check(80, 100).is_err());
+    }
+
+    #[test]
+    fn test_shadow_index_definition() {
+        assert_eq!(
+            shadow_index_definition(
+                "idx_service_requests_open",
+                "CREATE INDEX idx_service_requests_open ON public.service_requests USING btree (created_at) WHERE is_open"
+            )
+            .unwrap(),
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_open_shadow ON public.service_requests_shadow USING btree (created_at) WHERE is_open"
+        );
+        assert_eq!(
+            shadow_index_definition(
+                "idx_service_requests_borough",
+                "CREATE INDEX idx_service_requests_borough ON ONLY public.service_requests USING btree (borough)"
+            )
+            .unwrap(),
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_borough_shadow ON public.service_requests_shadow USING btree (borough)"
+        );
+        assert!(shadow_index_definition(
+            "idx_other",
+            "CREATE INDEX idx_other ON public.other USING btree (id)"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// Retry policy - exponential backoff for transient database failures
+use anyhow::Result;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_RETRY_BUDGET_SECS: u64 = 60;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// How often, and for how long, a database operation failing with a
+/// transient error is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first; 1 disables retries
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for each one after it
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Time after the first attempt past which no retry is started
+    pub budget: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            budget: Duration::from_secs(DEFAULT_RETRY_BUDGET_SECS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `attempt` until it succeeds, fails with an error that is not
+    /// [transient](is_transient), or the attempts or time budget run out.
+    pub async fn run<T, F, Fut>(&self, operation: &str, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let started = Instant::now();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            let delay = jitter(self.backoff(attempts));
+            if !is_transient(&error)
+                || attempts >= self.max_attempts
+                || started.elapsed() + delay > self.budget
+            {
+                return Err(if attempts > 1 {
+                    error.context(format!("{operation} failed after {attempts} attempts"))
+                } else {
+                    error
+                });
+            }
+
+            warn!(
+                operation,
+                attempt = attempts,
+                delay_ms = delay.as_millis() as u64,
+                "Transient database error, retrying: {:#}",
+                error
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Backoff after the given failed attempt (1-based), before jitter.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Picks a delay between half of `delay` and all of it, so clients that
+/// failed together do not retry in lockstep.
+fn jitter(delay: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let fraction = random as f64 / u64::MAX as f64;
+    delay / 2 + delay.mul_f64(fraction / 2.0)
+}
+
+/// True if `error` was caused by a failure worth retrying: a lost or
+/// refused connection, pool exhaustion, a deadlock or serialization failure,
+/// or the server shutting down. Constraint violations, type errors and
+/// other rejections of the statement itself are permanent.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<sqlx::Error>())
+        .any(is_transient_sqlx)
+}
+
+pub(crate) fn is_transient_sqlx(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(e) => e.code().is_some_and(|code| is_transient_sqlstate(&code)),
+        _ => false,
+    }
+}
+
+fn is_transient_sqlstate(code: &str) -> bool {
+    // Class 08 is connection exceptions
+    code.starts_with("08")
+        || matches!(
+            code,
+            // serialization_failure, deadlock_detected, too_many_connections
+            "40001" | "40P01" | "53300"
+            // admin_shutdown, crash_shutdown, cannot_connect_now
+            | "57P01" | "57P02" | "57P03"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    fn quick_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            budget: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_transient_classification() {
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(is_transient(
+            &anyhow::Error::new(sqlx::Error::Io(reset)).context("Failed to load chunk")
+        ));
+        assert!(is_transient(&sqlx::Error::PoolTimedOut.into()));
+        assert!(!is_transient(&sqlx::Error::RowNotFound.into()));
+        assert!(!is_transient(&anyhow!("constraint violated")));
+
+        assert!(is_transient_sqlstate("57P01"));
+        assert!(is_transient_sqlstate("40P01"));
+        assert!(is_transient_sqlstate("08006"));
+        assert!(!is_transient_sqlstate("23505"));
+        assert!(!is_transient_sqlstate("22P02"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(40), DEFAULT_MAX_BACKOFF);
+
+        for _ in 0..100 {
+            let delay = jitter(Duration::from_millis(100));
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success_or_permanent_error() {
+        let mut calls = 0;
+        let result = quick_policy(5)
+            .run("test", || {
+                calls += 1;
+                let fail = calls < 3;
+                async move {
+                    if fail {
+                        Err(sqlx::Error::PoolTimedOut).context("Failed to acquire connection")
+                    } else {
+                        Ok(calls)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<()> = quick_policy(5)
+            .run("test", || {
+                calls += 1;
+                async { Err(sqlx::Error::RowNotFound.into()) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let result: Result<()> = quick_policy(2)
+            .run("test", || {
+                calls += 1;
+                async { Err(sqlx::Error::PoolTimedOut.into()) }
+            })
+            .await;
+        assert!(format!("{:#}", result.unwrap_err()).contains("failed after 2 attempts"));
+        assert_eq!(calls, 2);
+    }
+}
@@ -1,72 +1,390 @@
 // Database schema definitions
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use sha2::{Digest, Sha256};
+use sqlx::{postgres::PgPoolOptions, PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use std::str::FromStr;
 use tracing::{info, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+use crate::clean::{FieldCorrection, QualityFlags};
+use crate::db::copy::CopyWriter;
+use crate::db::partition::Partitioning;
+use crate::db::retry::{is_transient_sqlx, RetryPolicy};
+
+pub(crate) const SERVICE_REQUESTS_TABLE: &str = "service_requests";
+const QUARANTINE_TABLE: &str = "service_requests_quarantine";
+/// Prefix of the unlogged tables chunks are copied into before being merged.
+const STAGING_PREFIX: &str = "etl_staging_";
+/// Encoded rows buffered before each COPY send.
+const COPY_BUFFER_BYTES: usize = 1 << 20;
+
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+/// Columns written by the loader, in COPY order; `unique_key` must stay first.
+const LOAD_COLUMNS: &[&str] = &[
+    "unique_key",
+    "created_at",
+    "closed_at",
+    "agency",
+    "complaint_type",
+    "complaint_category",
+    "complaint_subcategory",
+    "descriptor",
+    "resolution_description",
+    "borough",
+    "latitude",
+    "longitude",
+    "x_coordinate_state_plane",
+    "y_coordinate_state_plane",
+    "coordinate_repair",
+    "resolution_hours",
+    "created_local_date",
+    "created_hour",
+    "day_of_week",
+    "is_open",
+    "is_weekend",
+    "geohash",
+    "hex_cell",
+    "quality_flags",
+    "failed_rules",
+    "corrections",
+    "attributes",
+    "row_hash",
+];
+/// Columns snapshotted into `service_request_history` for each version.
+pub(crate) const HISTORY_COLUMNS: &[&str] = &[
+    "closed_at",
+    "agency",
+    "complaint_type",
+    "complaint_category",
+    "descriptor",
+    "resolution_description",
+    "borough",
+    "is_open",
+    "resolution_hours",
+    "row_hash",
+];
+const MATERIALIZED_VIEWS: &[&str] = &[
+    "mv_complaints_by_day_borough",
+    "mv_complaints_by_type_month",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ServiceRequest {
     pub unique_key: i64,
     pub created_at: DateTime<Utc>,
     pub closed_at: Option<DateTime<Utc>>,
+    pub agency: Option<String>,
     pub complaint_type: String,
+    pub complaint_category: Option<String>,
+    pub complaint_subcategory: Option<String>,
     pub descriptor: Option<String>,
+    /// Agency's free-text resolution note, with PII replaced by type tokens
+    pub resolution_description: Option<String>,
     pub borough: Option<String>,
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
+    /// NAD83 / New York Long Island (EPSG:2263) easting, US survey feet
+    pub x_coordinate_state_plane: Option<f64>,
+    /// NAD83 / New York Long Island (EPSG:2263) northing, US survey feet
+    pub y_coordinate_state_plane: Option<f64>,
+    /// How latitude/longitude were repaired, if they were
+    pub coordinate_repair: Option<String>,
+    pub resolution_hours: Option<f64>,
+    /// Calendar date of `created_at` in NYC local time
+    pub created_local_date: Option<NaiveDate>,
+    pub created_hour: Option<i16>,
+    /// ISO day of week (1 = Monday, 7 = Sunday) in NYC local time
+    pub day_of_week: Option<i16>,
+    pub is_open: Option<bool>,
+    pub is_weekend: Option<bool>,
+    pub geohash: Option<String>,
+    /// Hexagonal grid cell as `<cell size in meters>:<q>:<r>`
+    pub hex_cell: Option<String>,
+    /// Data quality issues found during transform; 0 means pristine
+    pub quality_flags: QualityFlags,
+    /// Validation rules this record failed under the `flag` policy
+    pub failed_rules: Vec<String>,
+    /// Categorical values rewritten during transform, kept for auditing
+    #[sqlx(json)]
+    pub corrections: Vec<FieldCorrection>,
+    /// Values of the configured derived columns, keyed by column name
+    #[sqlx(json)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+/// One version of a request in `service_request_history`; `valid_to` is
+/// `None` for the current version.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RequestVersion {
+    pub unique_key: i64,
+    pub valid_from: DateTime<Utc>,
+    pub valid_to: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub agency: Option<String>,
+    pub complaint_type: String,
+    pub complaint_category: Option<String>,
+    pub descriptor: Option<String>,
+    pub resolution_description: Option<String>,
+    pub borough: Option<String>,
+    pub is_open: Option<bool>,
+    pub resolution_hours: Option<f64>,
+    pub row_hash: Option<String>,
+}
+
+/// Names of one loader's unlogged staging tables, unique per instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagingTables {
+    pub requests: String,
+    pub quarantine: String,
+}
+
+impl StagingTables {
+    fn new() -> Self {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        Self {
+            requests: format!("{STAGING_PREFIX}{token}_requests"),
+            quarantine: format!("{STAGING_PREFIX}{token}_quarantine"),
+        }
+    }
+}
+
+/// Whether a run replaces the whole dataset or adds to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    /// Load into a shadow table that atomically replaces service_requests
+    #[default]
+    Full,
+    /// Load into service_requests directly
+    Incremental,
+}
+
+impl RunMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunMode::Full => "full",
+            RunMode::Incremental => "incremental",
+        }
+    }
+}
+
+impl FromStr for RunMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "full" => Ok(RunMode::Full),
+            "incremental" => Ok(RunMode::Incremental),
+            other => Err(anyhow!("Unknown run mode: {}", other)),
+        }
+    }
+}
+
+/// How rows whose `unique_key` already exists are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadMode {
+    /// Keep the stored row and skip the incoming one
+    #[default]
+    Insert,
+    /// Overwrite the stored row when its content hash differs
+    Upsert,
+}
+
+impl FromStr for LoadMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "insert" => Ok(LoadMode::Insert),
+            "upsert" => Ok(LoadMode::Upsert),
+            other => Err(anyhow!("Unknown load mode: {}", other)),
+        }
+    }
+}
+
+/// Row counts from loading a batch of records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadStats {
+    pub inserted: u64,
+    pub updated: u64,
+    /// Rows already stored with identical content (or skipped in insert mode)
+    pub unchanged: u64,
+    /// Records written to the quarantine table instead
+    pub quarantined: u64,
+    pub failed: u64,
+}
+
+impl LoadStats {
+    pub fn add(&mut self, other: &LoadStats) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+        self.quarantined += other.quarantined;
+        self.failed += other.failed;
+    }
+}
+
+impl ServiceRequest {
+    /// Appends this record as a COPY row in [`LOAD_COLUMNS`] order.
+    pub fn write_copy_row(&self, writer: &mut CopyWriter) {
+        writer
+            .plain(Some(self.unique_key))
+            .timestamp(Some(self.created_at))
+            .timestamp(self.closed_at)
+            .text(self.agency.as_deref())
+            .text(Some(&self.complaint_type))
+            .text(self.complaint_category.as_deref())
+            .text(self.complaint_subcategory.as_deref())
+            .text(self.descriptor.as_deref())
+            .text(self.resolution_description.as_deref())
+            .text(self.borough.as_deref())
+            .float(self.latitude)
+            .float(self.longitude)
+            .float(self.x_coordinate_state_plane)
+            .float(self.y_coordinate_state_plane)
+            .text(self.coordinate_repair.as_deref())
+            .float(self.resolution_hours)
+            .date(self.created_local_date)
+            .plain(self.created_hour)
+            .plain(self.day_of_week)
+            .bool(self.is_open)
+            .bool(self.is_weekend)
+            .text(self.geohash.as_deref())
+            .text(self.hex_cell.as_deref())
+            .plain(Some(self.quality_flags.bits()))
+            .text_array(&self.failed_rules)
+            .json(&self.corrections)
+            .json(&self.attributes)
+            .text(Some(&self.row_hash()));
+        writer.end_row();
+    }
+
+    /// SHA-256 over the record's loaded content, used to skip no-op updates.
+    pub fn row_hash(&self) -> String {
+        let content = serde_json::to_vec(self).expect("ServiceRequest serializes to JSON");
+        Sha256::digest(&content)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct Database {
     pool: PgPool,
+    /// Applied to every operation, and to connecting
+    retry: RetryPolicy,
 }
 
 impl Clone for Database {
     fn clone(&self) -> Self {
         Self {
             pool: self.pool.clone(),
+            retry: self.retry,
         }
     }
 }
 
 impl Database {
     pub async fn connect(database_url: &str) -> Result<Self> {
+        Self::connect_with(
+            database_url,
+            DEFAULT_MAX_CONNECTIONS,
+            RetryPolicy::default(),
+        )
+        .await
+    }
+
+    /// Connects with a pool of up to `max_connections`, retrying the
+    /// connection and every later operation under `retry`.
+    pub async fn connect_with(
+        database_url: &str,
+        max_connections: u32,
+        retry: RetryPolicy,
+    ) -> Result<Self> {
         info!("Connecting to database...");
-        
-        let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(database_url)
-            .await
-            .context("Failed to connect to PostgreSQL")?;
+
+        let pool = retry
+            .run("connect", || async {
+                PgPoolOptions::new()
+                    .max_connections(max_connections)
+                    .connect(database_url)
+                    .await
+                    .context("Failed to connect to PostgreSQL")
+            })
+            .await?;
 
         info!("Database connection established");
-        Ok(Self { pool })
+        Ok(Self { pool, retry })
+    }
+
+    pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
     }
 
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
-    pub async fn initialize_schema(&self) -> Result<()> {
+    /// Creates every table, index and view that does not exist yet; safe
+    /// to run again, so it is retried as a whole. `partitioning` only
+    /// applies if service_requests is created.
+    pub async fn initialize_schema(&self, partitioning: Partitioning) -> Result<()> {
+        self.retry
+            .run("initialize schema", || self.create_schema(partitioning))
+            .await
+    }
+
+    async fn create_schema(&self, partitioning: Partitioning) -> Result<()> {
         info!("Initializing database schema...");
 
+        // A partitioned table's primary key has to include the partition key
+        let (primary_key, partition_by) = match partitioning {
+            Partitioning::None => ("unique_key", ""),
+            Partitioning::Monthly => ("unique_key, created_at", "PARTITION BY RANGE (created_at)"),
+        };
+
         // Create service_requests table
-        sqlx::query(
+        sqlx::query(&format!(
             r#"
             CREATE TABLE IF NOT EXISTS service_requests (
-                unique_key BIGINT PRIMARY KEY,
+                unique_key BIGINT NOT NULL,
                 created_at TIMESTAMPTZ NOT NULL,
                 closed_at TIMESTAMPTZ,
+                agency TEXT,
                 complaint_type TEXT NOT NULL,
+                complaint_category TEXT,
+                complaint_subcategory TEXT,
                 descriptor TEXT,
+                resolution_description TEXT,
                 borough TEXT CHECK (borough IN ('BRONX', 'BROOKLYN', 'MANHATTAN', 'QUEENS', 'STATEN ISLAND')),
                 latitude DOUBLE PRECISION,
                 longitude DOUBLE PRECISION,
-                ingested_at TIMESTAMPTZ DEFAULT now()
-            )
+                x_coordinate_state_plane DOUBLE PRECISION,
+                y_coordinate_state_plane DOUBLE PRECISION,
+                coordinate_repair TEXT CHECK (coordinate_repair IN ('swapped', 'placeholder_removed', 'state_plane_converted')),
+                resolution_hours DOUBLE PRECISION,
+                created_local_date DATE,
+                created_hour SMALLINT,
+                day_of_week SMALLINT,
+                is_open BOOLEAN,
+                is_weekend BOOLEAN,
+                geohash TEXT,
+                hex_cell TEXT,
+                quality_flags INTEGER NOT NULL DEFAULT 0,
+                failed_rules TEXT[] NOT NULL DEFAULT '{{}}',
+                corrections JSONB NOT NULL DEFAULT '[]',
+                attributes JSONB NOT NULL DEFAULT '{{}}',
+                row_hash TEXT,
+                updated_at TIMESTAMPTZ,
+                ingested_at TIMESTAMPTZ DEFAULT now(),
+                PRIMARY KEY ({primary_key})
+            ) {partition_by}
             "#,
-        )
+        ))
         .execute(&self.pool)
         .await
         .context("Failed to create service_requests table")?;
@@ -86,6 +404,134 @@ impl Database {
         .await
         .context("Failed to create borough index")?;
 
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_complaint_category ON service_requests(complaint_category)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create complaint_category index")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_created_local_date ON service_requests(created_local_date)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create created_local_date index")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_day_hour ON service_requests(day_of_week, created_hour)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create day_of_week/created_hour index")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_open ON service_requests(created_at) WHERE is_open",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create open requests index")?;
+
+        // text_pattern_ops lets coarser cells be queried by prefix
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_geohash ON service_requests(geohash text_pattern_ops)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create geohash index")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_hex_cell ON service_requests(hex_cell)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create hex_cell index")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_requests_pristine ON service_requests(created_at) WHERE quality_flags = 0",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create pristine rows index")?;
+
+        // Records held back by the quarantine rule policy; same columns
+        // without the CHECK constraints the failing values may violate
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS service_requests_quarantine (
+                LIKE service_requests INCLUDING DEFAULTS,
+                PRIMARY KEY (unique_key)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create service_requests_quarantine table")?;
+
+        // One row per version of a request (SCD type 2), maintained by the
+        // loader whenever a stored row is inserted or changes
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS service_request_history (
+                history_id BIGSERIAL PRIMARY KEY,
+                unique_key BIGINT NOT NULL,
+                valid_from TIMESTAMPTZ NOT NULL,
+                valid_to TIMESTAMPTZ,
+                closed_at TIMESTAMPTZ,
+                agency TEXT,
+                complaint_type TEXT NOT NULL,
+                complaint_category TEXT,
+                descriptor TEXT,
+                resolution_description TEXT,
+                borough TEXT,
+                is_open BOOLEAN,
+                resolution_hours DOUBLE PRECISION,
+                row_hash TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create service_request_history table")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_service_request_history_key ON service_request_history(unique_key, valid_from)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create history key index")?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_service_request_history_current ON service_request_history(unique_key) WHERE valid_to IS NULL",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create current history version index")?;
+
+        // Lookup table so analysts can decode quality_flags bits in SQL
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS quality_flag_definitions (
+                bit INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create quality_flag_definitions table")?;
+
+        for (flag, name) in QualityFlags::ALL {
+            sqlx::query(
+                "INSERT INTO quality_flag_definitions (bit, name) VALUES ($1, $2) ON CONFLICT (bit) DO UPDATE SET name = EXCLUDED.name",
+            )
+            .bind(flag.bits())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .context("Failed to seed quality_flag_definitions")?;
+        }
+
         // Create ETL watermarks table
         sqlx::query(
             r#"
@@ -95,12 +541,14 @@ impl Database {
                 last_created_at TIMESTAMPTZ,
                 last_unique_key BIGINT,
                 run_mode TEXT NOT NULL CHECK (run_mode IN ('full', 'incremental')),
+                input TEXT,
                 rows_processed BIGINT NOT NULL DEFAULT 0,
                 rows_inserted BIGINT NOT NULL DEFAULT 0,
                 rows_skipped BIGINT NOT NULL DEFAULT 0,
                 started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
                 completed_at TIMESTAMPTZ,
-                status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'failed'))
+                status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'failed')),
+                error_message TEXT
             )
             "#,
         )
@@ -108,17 +556,25 @@ impl Database {
         .await
         .context("Failed to create etl_watermarks table")?;
 
+        // start_run looks runs up by the client-generated run_id
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_etl_watermarks_run_id ON etl_watermarks(run_id)",
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create run_id index")?;
+
         // Create materialized views
         sqlx::query(
             r#"
             CREATE MATERIALIZED VIEW IF NOT EXISTS mv_complaints_by_day_borough AS
             SELECT 
-                DATE(created_at) as complaint_date,
+                created_local_date as complaint_date,
                 borough,
                 COUNT(*) as complaint_count
             FROM service_requests
             WHERE borough IS NOT NULL
-            GROUP BY DATE(created_at), borough
+            GROUP BY created_local_date, borough
             ORDER BY complaint_date DESC, borough
             "#,
         )
@@ -126,69 +582,557 @@ impl Database {
         .await
         .context("Failed to create materialized view")?;
 
+        // Group by canonical category; unmapped types fall back to the raw value
+        // so they remain visible until the taxonomy covers them
+        sqlx::query(
+            r#"
+            CREATE MATERIALIZED VIEW IF NOT EXISTS mv_complaints_by_type_month AS
+            SELECT
+                DATE_TRUNC('month', created_local_date)::date as month,
+                COALESCE(complaint_category, complaint_type) as complaint_category,
+                complaint_subcategory,
+                COUNT(*) as complaint_count,
+                AVG(resolution_hours) as avg_resolution_hours
+            FROM service_requests
+            GROUP BY 1, 2, 3
+            ORDER BY month DESC, complaint_count DESC
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create materialized view")?;
+
         info!("Database schema initialized successfully");
         Ok(())
     }
 
     pub async fn refresh_materialized_views(&self, concurrently: bool) -> Result<()> {
         let concurrently_str = if concurrently { "CONCURRENTLY" } else { "" };
-        
-        info!("Refreshing materialized views {}...", if concurrently { "concurrently" } else { "" });
 
-        sqlx::query(&format!(
-            "REFRESH MATERIALIZED VIEW {} mv_complaints_by_day_borough",
-            concurrently_str
-        ))
-        .execute(&self.pool)
-        .await
-        .context("Failed to refresh materialized view")?;
+        info!(
+            "Refreshing materialized views {}...",
+            if concurrently { "concurrently" } else { "" }
+        );
+
+        for view in MATERIALIZED_VIEWS {
+            let sql = format!("REFRESH MATERIALIZED VIEW {} {}", concurrently_str, view);
+            self.retry
+                .run("refresh materialized view", || async {
+                    sqlx::query(&sql)
+                        .execute(&self.pool)
+                        .await
+                        .context(format!("Failed to refresh materialized view {}", view))
+                })
+                .await?;
+        }
 
         info!("Materialized views refreshed successfully");
         Ok(())
     }
 
-    pub async fn bulk_insert(&self, records: &[ServiceRequest]) -> Result<u64> {
-        if records.is_empty() {
-            return Ok(0);
+    /// Loads a single batch through its own short-lived staging tables.
+    pub async fn bulk_insert(
+        &self,
+        records: &[ServiceRequest],
+        mode: LoadMode,
+    ) -> Result<LoadStats> {
+        let staging = self.create_staging_tables().await?;
+        let result = self
+            .load_chunk(SERVICE_REQUESTS_TABLE, &staging, records, &[], mode)
+            .await;
+        self.drop_staging_tables(&staging).await?;
+        result
+    }
+
+    /// Creates a fresh pair of unlogged staging tables. They are ordinary
+    /// tables so every pooled connection can see them; the caller drops them
+    /// with [`drop_staging_tables`](Self::drop_staging_tables).
+    pub async fn create_staging_tables(&self) -> Result<StagingTables> {
+        let staging = StagingTables::new();
+        for (name, table) in [
+            (&staging.requests, SERVICE_REQUESTS_TABLE),
+            (&staging.quarantine, QUARANTINE_TABLE),
+        ] {
+            let sql = format!(
+                "CREATE UNLOGGED TABLE IF NOT EXISTS {name} (LIKE {table} INCLUDING DEFAULTS)"
+            );
+            self.retry
+                .run("create staging table", || async {
+                    sqlx::query(&sql)
+                        .execute(&self.pool)
+                        .await
+                        .with_context(|| format!("Failed to create staging table {name}"))
+                })
+                .await?;
+        }
+
+        info!(requests = %staging.requests, quarantine = %staging.quarantine, "Created staging tables");
+        Ok(staging)
+    }
+
+    pub async fn drop_staging_tables(&self, staging: &StagingTables) -> Result<()> {
+        let sql = format!(
+            "DROP TABLE IF EXISTS {}, {}",
+            staging.requests, staging.quarantine
+        );
+        self.retry
+            .run("drop staging tables", || async {
+                sqlx::query(&sql)
+                    .execute(&self.pool)
+                    .await
+                    .context("Failed to drop staging tables")
+            })
+            .await?;
+
+        info!(requests = %staging.requests, quarantine = %staging.quarantine, "Dropped staging tables");
+        Ok(())
+    }
+
+    /// Loads one chunk in a single transaction: `records` into `target`
+    /// (service_requests, with history versions, or a full refresh's shadow
+    /// table) and `quarantined` into the quarantine table. Rows the database
+    /// rejects are isolated and counted as failed; everything else commits
+    /// together. A transient failure rolls the chunk back and retries it.
+    pub async fn load_chunk(
+        &self,
+        target: &str,
+        staging: &StagingTables,
+        records: &[ServiceRequest],
+        quarantined: &[ServiceRequest],
+        mode: LoadMode,
+    ) -> Result<LoadStats> {
+        self.retry
+            .run("load chunk", || {
+                self.try_load_chunk(target, staging, records, quarantined, mode)
+            })
+            .await
+    }
+
+    async fn try_load_chunk(
+        &self,
+        target: &str,
+        staging: &StagingTables,
+        records: &[ServiceRequest],
+        quarantined: &[ServiceRequest],
+        mode: LoadMode,
+    ) -> Result<LoadStats> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut stats = merge_into(&mut tx, target, &staging.requests, records, mode).await?;
+        let held = merge_into(
+            &mut tx,
+            QUARANTINE_TABLE,
+            &staging.quarantine,
+            quarantined,
+            mode,
+        )
+        .await?;
+        stats.quarantined = held.inserted + held.updated;
+        stats.failed += held.failed;
+
+        tx.commit().await.context("Failed to commit chunk")?;
+        Ok(stats)
+    }
+
+    /// Every stored version of a request, oldest first.
+    pub async fn request_history(&self, unique_key: i64) -> Result<Vec<RequestVersion>> {
+        let sql = format!(
+            "SELECT unique_key, valid_from, valid_to, {} FROM service_request_history \
+             WHERE unique_key = $1 ORDER BY valid_from, history_id",
+            HISTORY_COLUMNS.join(", ")
+        );
+
+        self.retry
+            .run("fetch request history", || async {
+                sqlx::query_as(&sql)
+                    .bind(unique_key)
+                    .fetch_all(&self.pool)
+                    .await
+                    .context("Failed to fetch request history")
+            })
+            .await
+    }
+
+    pub async fn get_record_count(&self) -> Result<i64> {
+        let row: (i64,) = self
+            .retry
+            .run("count records", || async {
+                sqlx::query_as("SELECT COUNT(*) FROM service_requests")
+                    .fetch_one(&self.pool)
+                    .await
+                    .context("Failed to get record count")
+            })
+            .await?;
+
+        Ok(row.0)
+    }
+}
+
+/// Streams `records` into the (emptied) staging table with COPY, then merges
+/// them into `table` with one set-based statement.
+async fn merge_into(
+    tx: &mut Transaction<'_, Postgres>,
+    table: &str,
+    staging: &str,
+    records: &[ServiceRequest],
+    mode: LoadMode,
+) -> Result<LoadStats> {
+    let mut stats = LoadStats::default();
+    if records.is_empty() {
+        return Ok(stats);
+    }
+
+    let columns = LOAD_COLUMNS.join(", ");
+
+    // Left over from the previous chunk, or from one that was rolled back
+    sqlx::query(&format!("TRUNCATE {staging}"))
+        .execute(&mut **tx)
+        .await
+        .context("Failed to truncate staging table")?;
+
+    let mut copy = tx
+        .copy_in_raw(&format!("COPY {staging} ({columns}) FROM STDIN"))
+        .await
+        .context("Failed to start COPY into staging table")?;
+    let mut writer = CopyWriter::new();
+    for record in records {
+        record.write_copy_row(&mut writer);
+        if writer.len() >= COPY_BUFFER_BYTES {
+            copy.send(writer.take()).await?;
         }
+    }
+    if !writer.is_empty() {
+        copy.send(writer.take()).await?;
+    }
+    let staged = copy.finish().await.context("Failed to COPY records")?;
 
-        let mut inserted = 0;
-        
-        for record in records {
-            let result = sqlx::query(
-                r#"
-                INSERT INTO service_requests 
-                (unique_key, created_at, closed_at, complaint_type, descriptor, borough, latitude, longitude)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                ON CONFLICT (unique_key) DO NOTHING
-                "#,
+    // A partitioned table's primary key also covers created_at, so a key
+    // whose created_at changed would not conflict. Insert skips keys that
+    // are already stored under any created_at; upsert first moves the stored
+    // row to the incoming created_at (clearing row_hash, so the merge
+    // overwrites it and counts it as updated).
+    let (existing, move_sql) = match mode {
+        LoadMode::Insert => (
+            format!("AND NOT EXISTS (SELECT 1 FROM {table} t WHERE t.unique_key = s.unique_key)"),
+            None,
+        ),
+        LoadMode::Upsert => (
+            String::new(),
+            Some(format!(
+                "UPDATE {table} t SET created_at = s.created_at, row_hash = NULL \
+                 FROM (SELECT DISTINCT ON (unique_key) unique_key, created_at FROM {staging} \
+                       WHERE unique_key = ANY($1) ORDER BY unique_key, ctid) s \
+                 WHERE t.unique_key = s.unique_key AND t.created_at <> s.created_at"
+            )),
+        ),
+    };
+    let conflict = match mode {
+        LoadMode::Insert => "DO NOTHING".to_string(),
+        LoadMode::Upsert => {
+            let assignments: Vec<String> = LOAD_COLUMNS[1..]
+                .iter()
+                .map(|column| format!("{column} = EXCLUDED.{column}"))
+                .collect();
+            format!(
+                "DO UPDATE SET {}, updated_at = now() WHERE {table}.row_hash IS DISTINCT FROM EXCLUDED.row_hash",
+                assignments.join(", ")
             )
-            .bind(record.unique_key)
-            .bind(record.created_at)
-            .bind(record.closed_at)
-            .bind(&record.complaint_type)
-            .bind(&record.descriptor)
-            .bind(&record.borough)
-            .bind(record.latitude)
-            .bind(record.longitude)
-            .execute(&self.pool)
-            .await;
+        }
+    };
+    // DISTINCT ON keeps the first copy of a key repeated within the chunk,
+    // which DO UPDATE could not touch twice. Only DO UPDATE sets updated_at,
+    // so it is null for freshly inserted rows (xmax cannot be read from a
+    // partitioned table); rows skipped by DO NOTHING or the row_hash guard
+    // are not returned at all.
+    let merge_sql = format!(
+        "INSERT INTO {table} ({columns}) \
+         SELECT DISTINCT ON (unique_key) {columns} FROM {staging} s \
+         WHERE s.unique_key = ANY($1) {existing} ORDER BY unique_key, ctid \
+         ON CONFLICT ON CONSTRAINT {table}_pkey {conflict} \
+         RETURNING unique_key, (updated_at IS NULL) AS inserted"
+    );
+
+    let mut keys: Vec<i64> = records.iter().map(|record| record.unique_key).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let (merged, failed) = merge_isolating(tx, move_sql.as_deref(), &merge_sql, keys)
+        .await
+        .with_context(|| format!("Failed to merge staged records into {table}"))?;
+
+    for (_, inserted) in &merged {
+        if *inserted {
+            stats.inserted += 1;
+        } else {
+            stats.updated += 1;
+        }
+    }
+    for (unique_key, error) in &failed {
+        warn!(
+            unique_key,
+            table, "Record rejected by the database: {}", error
+        );
+    }
+    let failed_keys: HashSet<i64> = failed.iter().map(|(key, _)| *key).collect();
+    stats.failed = records
+        .iter()
+        .filter(|record| failed_keys.contains(&record.unique_key))
+        .count() as u64;
+    stats.unchanged = staged - stats.inserted - stats.updated - stats.failed;
+
+    if table == SERVICE_REQUESTS_TABLE && !merged.is_empty() {
+        let keys: Vec<i64> = merged.iter().map(|(key, _)| *key).collect();
+        record_versions(tx, &keys).await?;
+    }
+
+    Ok(stats)
+}
+
+/// Runs the merge for `keys`, preceded by `move_sql` if given, bisecting
+/// under savepoints whenever it fails so that only keys failing on their own
+/// are left out. Returns the merged rows and each failing key with its
+/// error. Transient errors are not the rows' fault and fail the whole merge
+/// so the chunk can be retried.
+async fn merge_isolating(
+    tx: &mut Transaction<'_, Postgres>,
+    move_sql: Option<&str>,
+    merge_sql: &str,
+    keys: Vec<i64>,
+) -> Result<(Vec<(i64, bool)>, Vec<(i64, String)>)> {
+    let mut merged = Vec::new();
+    let mut failed = Vec::new();
+    let mut pending = vec![keys];
 
-            match result {
-                Ok(result) => inserted += result.rows_affected(),
-                Err(e) => warn!("Failed to insert record {}: {}", record.unique_key, e),
+    while let Some(batch) = pending.pop() {
+        sqlx::query("SAVEPOINT merge_batch")
+            .execute(&mut **tx)
+            .await?;
+        let result = async {
+            if let Some(move_sql) = move_sql {
+                sqlx::query(move_sql)
+                    .bind(&batch)
+                    .execute(&mut **tx)
+                    .await?;
             }
+            sqlx::query_as::<_, (i64, bool)>(merge_sql)
+                .bind(&batch)
+                .fetch_all(&mut **tx)
+                .await
         }
+        .await;
 
-        Ok(inserted)
+        match result {
+            Ok(rows) => {
+                sqlx::query("RELEASE SAVEPOINT merge_batch")
+                    .execute(&mut **tx)
+                    .await?;
+                merged.extend(rows);
+            }
+            Err(e) if is_transient_sqlx(&e) => return Err(e.into()),
+            Err(e) => {
+                sqlx::query("ROLLBACK TO SAVEPOINT merge_batch")
+                    .execute(&mut **tx)
+                    .await?;
+                if batch.len() == 1 {
+                    failed.push((batch[0], e.to_string()));
+                } else {
+                    let (head, tail) = batch.split_at(batch.len() / 2);
+                    pending.push(tail.to_vec());
+                    pending.push(head.to_vec());
+                }
+            }
+        }
     }
 
-    pub async fn get_record_count(&self) -> Result<i64> {
-        let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM service_requests")
-            .fetch_one(&self.pool)
+    Ok((merged, failed))
+}
+
+/// Closes the current history version of each key and opens a new one from
+/// the stored row. `now()` is the transaction start, so the closed version's
+/// valid_to, the new version's valid_from and `updated_at` all agree.
+async fn record_versions(tx: &mut Transaction<'_, Postgres>, keys: &[i64]) -> Result<()> {
+    sqlx::query(
+        "UPDATE service_request_history SET valid_to = now() WHERE unique_key = ANY($1) AND valid_to IS NULL",
+    )
+    .bind(keys)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to close history versions")?;
+
+    let columns = HISTORY_COLUMNS.join(", ");
+    sqlx::query(&format!(
+        "INSERT INTO service_request_history (unique_key, valid_from, {columns}) \
+         SELECT unique_key, now(), {columns} FROM service_requests WHERE unique_key = ANY($1)"
+    ))
+    .bind(keys)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to record history versions")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_row_hash_tracks_content() {
+        let open = ServiceRequest {
+            unique_key: 1,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap(),
+            complaint_type: "Noise".to_string(),
+            ..Default::default()
+        };
+        let closed = ServiceRequest {
+            closed_at: Some(Utc.with_ymd_and_hms(2025, 1, 2, 10, 0, 0).unwrap()),
+            ..open.clone()
+        };
+
+        assert_eq!(open.row_hash(), open.clone().row_hash());
+        assert_eq!(open.row_hash().len(), 64);
+        assert_ne!(open.row_hash(), closed.row_hash());
+    }
+
+    #[test]
+    fn test_load_mode_parse() {
+        assert_eq!("upsert".parse::<LoadMode>().unwrap(), LoadMode::Upsert);
+        assert_eq!(" Insert ".parse::<LoadMode>().unwrap(), LoadMode::Insert);
+        assert!("merge".parse::<LoadMode>().is_err());
+    }
+
+    #[test]
+    fn test_run_mode_parse() {
+        assert_eq!("FULL".parse::<RunMode>().unwrap(), RunMode::Full);
+        let incremental: RunMode = "incremental".parse().unwrap();
+        assert_eq!(incremental.as_str(), "incremental");
+        assert!("delta".parse::<RunMode>().is_err());
+    }
+
+    /// Held by tests that recreate the schema, so they do not run at once.
+    pub(crate) static SCHEMA_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Drops and recreates service_requests and its history in the database
+    /// at TEST_DATABASE_URL, so only point it at a disposable one.
+    async fn recreate_schema(partitioning: Partitioning) -> Database {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let db = Database::connect(&url).await.unwrap();
+        sqlx::query(
+            "DROP TABLE IF EXISTS service_requests, service_requests_quarantine, \
+             service_request_history CASCADE",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+        db.initialize_schema(partitioning).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a disposable PostgreSQL database at TEST_DATABASE_URL"]
+    async fn test_flagged_borough_is_loaded() {
+        let _lock = SCHEMA_LOCK.lock().await;
+        let db = recreate_schema(Partitioning::None).await;
+        let mut pipeline = crate::etl::Pipeline::builder()
+            .with_policies(crate::clean::RulePolicies::parse("borough=flag").unwrap())
+            .build()
+            .unwrap();
+        let records = pipeline
+            .transform(vec![ServiceRequest {
+                unique_key: 1,
+                created_at: Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap(),
+                complaint_type: "Noise".to_string(),
+                borough: Some("ATLANTIS".to_string()),
+                ..Default::default()
+            }])
+            .unwrap();
+
+        let staging = db.create_staging_tables().await.unwrap();
+        let stats = db
+            .load_chunk(
+                SERVICE_REQUESTS_TABLE,
+                &staging,
+                &records,
+                &[],
+                LoadMode::Insert,
+            )
             .await
-            .context("Failed to get record count")?;
-        
-        Ok(row.0)
+            .unwrap();
+        db.drop_staging_tables(&staging).await.unwrap();
+
+        assert_eq!((stats.inserted, stats.failed), (1, 0));
+        let (borough, failed_rules): (Option<String>, Vec<String>) = sqlx::query_as(
+            "SELECT borough, failed_rules FROM service_requests WHERE unique_key = 1",
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap();
+        assert_eq!(borough, None);
+        assert_eq!(failed_rules, vec!["borough"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a disposable PostgreSQL database at TEST_DATABASE_URL"]
+    async fn test_changed_created_at_keeps_one_row_per_key() {
+        let _lock = SCHEMA_LOCK.lock().await;
+        let db = recreate_schema(Partitioning::Monthly).await;
+
+        let january = ServiceRequest {
+            unique_key: 1,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 31, 23, 0, 0).unwrap(),
+            complaint_type: "Noise".to_string(),
+            ..Default::default()
+        };
+        let february = ServiceRequest {
+            created_at: Utc.with_ymd_and_hms(2025, 2, 1, 1, 0, 0).unwrap(),
+            ..january.clone()
+        };
+        let months: Vec<_> = [&january, &february]
+            .iter()
+            .map(|record| crate::db::PartitionMonth::of(record.created_at))
+            .collect();
+        db.create_partitions(SERVICE_REQUESTS_TABLE, &months)
+            .await
+            .unwrap();
+        let staging = db.create_staging_tables().await.unwrap();
+        let load = |record: &ServiceRequest, mode| {
+            let records = vec![record.clone()];
+            let (db, staging) = (&db, &staging);
+            async move {
+                db.load_chunk(SERVICE_REQUESTS_TABLE, staging, &records, &[], mode)
+                    .await
+                    .unwrap()
+            }
+        };
+        let stored = || async {
+            let rows: Vec<(DateTime<Utc>,)> =
+                sqlx::query_as("SELECT created_at FROM service_requests WHERE unique_key = 1")
+                    .fetch_all(db.pool())
+                    .await
+                    .unwrap();
+            rows.into_iter()
+                .map(|(created_at,)| created_at)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(load(&january, LoadMode::Insert).await.inserted, 1);
+
+        // Insert keeps the stored row
+        assert_eq!(load(&february, LoadMode::Insert).await.unchanged, 1);
+        assert_eq!(stored().await, vec![january.created_at]);
+
+        // Upsert moves it to the new month, with one current history version
+        assert_eq!(load(&february, LoadMode::Upsert).await.updated, 1);
+        assert_eq!(stored().await, vec![february.created_at]);
+        let (versions, current): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COUNT(*) FILTER (WHERE valid_to IS NULL) \
+             FROM service_request_history WHERE unique_key = 1",
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap();
+        assert_eq!((versions, current), (2, 1));
+
+        db.drop_staging_tables(&staging).await.unwrap();
     }
 }
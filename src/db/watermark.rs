@@ -0,0 +1,380 @@
+// Run records - each run's lifecycle and the watermark incremental runs resume from
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::schema::{Database, RunMode, ServiceRequest};
+
+pub const DEFAULT_WATERMARK_LOOKBACK_HOURS: i64 = 24;
+
+/// Position of the newest record a run loaded, ordered by `created_at` and
+/// then `unique_key`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Watermark {
+    pub created_at: DateTime<Utc>,
+    pub unique_key: i64,
+}
+
+impl Watermark {
+    pub fn of(record: &ServiceRequest) -> Self {
+        Self {
+            created_at: record.created_at,
+            unique_key: record.unique_key,
+        }
+    }
+
+    /// True if `record` is at or below this watermark.
+    pub fn covers(&self, record: &ServiceRequest) -> bool {
+        Self::of(record) <= *self
+    }
+
+    /// The watermark moved back by `window`, so records created up to that
+    /// long before it are loaded again.
+    pub fn lookback(&self, window: Duration) -> Self {
+        Self {
+            created_at: self.created_at - window,
+            ..*self
+        }
+    }
+
+    /// The highest watermark that does not cover this position.
+    pub fn just_below(&self) -> Self {
+        Self {
+            unique_key: self.unique_key - 1,
+            ..*self
+        }
+    }
+}
+
+/// How far a run's watermark may advance, given its chunks in any order.
+///
+/// It never passes a record of a chunk with failed records, so those are
+/// loaded again by the next incremental run even if later chunks committed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HighWater {
+    /// Newest record committed
+    loaded: Option<Watermark>,
+    /// Oldest record of a chunk with failures
+    failed_from: Option<Watermark>,
+}
+
+impl HighWater {
+    /// Starts from the watermark an incremental run resumed from, so it is
+    /// kept when the run loads nothing newer.
+    pub fn resuming_from(previous: Option<Watermark>) -> Self {
+        Self {
+            loaded: previous,
+            failed_from: None,
+        }
+    }
+
+    pub fn add(&mut self, loaded: Option<Watermark>, failed_from: Option<Watermark>) {
+        self.loaded = self.loaded.max(loaded);
+        self.failed_from = match (self.failed_from, failed_from) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Watermark the run may record, if it committed any record below the
+    /// first failure. A failure below the watermark the run resumed from
+    /// moves it back, so the next run retries the failed records.
+    pub fn watermark(&self) -> Option<Watermark> {
+        match (self.loaded, self.failed_from) {
+            (Some(loaded), Some(failed_from)) => Some(loaded.min(failed_from.just_below())),
+            (loaded, _) => loaded,
+        }
+    }
+}
+
+/// Row counts recorded for a run as its chunks commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunCounts {
+    /// Records of the chunks transformed so far
+    pub processed: u64,
+    pub inserted: u64,
+    /// Records the transform dropped, including those below the watermark
+    pub skipped: u64,
+}
+
+/// One run's row in `etl_watermarks`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RunRecord {
+    pub id: i32,
+    pub run_mode: String,
+    pub input: Option<String>,
+    /// `running`, `completed` or `failed`
+    pub status: String,
+    pub rows_processed: i64,
+    pub rows_inserted: i64,
+    pub rows_skipped: i64,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub last_created_at: Option<DateTime<Utc>>,
+    pub last_unique_key: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+impl RunRecord {
+    /// Time from start to completion, or `None` while the run is still going.
+    pub fn duration(&self) -> Option<Duration> {
+        self.completed_at
+            .map(|completed_at| completed_at - self.started_at)
+    }
+}
+
+impl Database {
+    /// The most recently started run, whatever its status.
+    pub async fn latest_run(&self) -> Result<Option<RunRecord>> {
+        self.retry_policy()
+            .run("read latest run", || async {
+                Ok(sqlx::query_as(
+                    "SELECT id, run_mode, input, status, rows_processed, rows_inserted, \
+                            rows_skipped, started_at, completed_at, last_created_at, \
+                            last_unique_key, error_message \
+                     FROM etl_watermarks ORDER BY started_at DESC, id DESC LIMIT 1",
+                )
+                .fetch_optional(self.pool())
+                .await?)
+            })
+            .await
+            .context("Failed to read the latest run")
+    }
+
+    /// Watermark of the most recent completed run that recorded one.
+    pub async fn latest_watermark(&self) -> Result<Option<Watermark>> {
+        let row: Option<(DateTime<Utc>, i64)> = self
+            .retry_policy()
+            .run("read watermark", || async {
+                Ok(sqlx::query_as(
+                    "SELECT last_created_at, last_unique_key FROM etl_watermarks \
+                     WHERE status = 'completed' \
+                       AND last_created_at IS NOT NULL AND last_unique_key IS NOT NULL \
+                     ORDER BY completed_at DESC, id DESC LIMIT 1",
+                )
+                .fetch_optional(self.pool())
+                .await?)
+            })
+            .await
+            .context("Failed to read the latest watermark")?;
+
+        Ok(row.map(|(created_at, unique_key)| Watermark {
+            created_at,
+            unique_key,
+        }))
+    }
+
+    /// Records the start of a run and returns its id; the row stays
+    /// `running` until [`complete_run`](Self::complete_run) or
+    /// [`fail_run`](Self::fail_run).
+    ///
+    /// The run_id is generated here, so a retry after a commit whose
+    /// acknowledgement was lost finds the row instead of inserting another.
+    pub async fn start_run(&self, mode: RunMode, input: &str) -> Result<i32> {
+        self.insert_run(uuid::Uuid::new_v4(), mode, input).await
+    }
+
+    /// Inserts the `running` row for `run_id` unless it exists, returning
+    /// its id either way.
+    async fn insert_run(&self, run_id: uuid::Uuid, mode: RunMode, input: &str) -> Result<i32> {
+        let (id,): (i32,) = self
+            .retry_policy()
+            .run("record run start", || async {
+                Ok(sqlx::query_as(
+                    "WITH inserted AS ( \
+                         INSERT INTO etl_watermarks (run_id, run_mode, input, status) \
+                         SELECT $1, $2, $3, 'running' \
+                         WHERE NOT EXISTS (SELECT 1 FROM etl_watermarks WHERE run_id = $1) \
+                         RETURNING id \
+                     ) \
+                     SELECT id FROM inserted \
+                     UNION ALL SELECT id FROM etl_watermarks WHERE run_id = $1",
+                )
+                .bind(run_id)
+                .bind(mode.as_str())
+                .bind(input)
+                .fetch_one(self.pool())
+                .await?)
+            })
+            .await
+            .context("Failed to record run start")?;
+
+        Ok(id)
+    }
+
+    pub async fn update_run(&self, run_id: i32, counts: &RunCounts) -> Result<()> {
+        self.retry_policy()
+            .run("record run progress", || async {
+                sqlx::query(
+                    "UPDATE etl_watermarks \
+                     SET rows_processed = $2, rows_inserted = $3, rows_skipped = $4 \
+                     WHERE id = $1",
+                )
+                .bind(run_id)
+                .bind(counts.processed as i64)
+                .bind(counts.inserted as i64)
+                .bind(counts.skipped as i64)
+                .execute(self.pool())
+                .await?;
+                Ok(())
+            })
+            .await
+            .context("Failed to record run progress")
+    }
+
+    /// Marks a run completed with the watermark the next incremental run
+    /// starts from.
+    pub async fn complete_run(
+        &self,
+        run_id: i32,
+        watermark: Option<Watermark>,
+        counts: &RunCounts,
+    ) -> Result<()> {
+        self.retry_policy()
+            .run("record run completion", || async {
+                sqlx::query(
+                    "UPDATE etl_watermarks \
+                     SET rows_processed = $2, rows_inserted = $3, rows_skipped = $4, \
+                         last_created_at = $5, last_unique_key = $6, \
+                         status = 'completed', completed_at = now() \
+                     WHERE id = $1",
+                )
+                .bind(run_id)
+                .bind(counts.processed as i64)
+                .bind(counts.inserted as i64)
+                .bind(counts.skipped as i64)
+                .bind(watermark.map(|w| w.created_at))
+                .bind(watermark.map(|w| w.unique_key))
+                .execute(self.pool())
+                .await?;
+                Ok(())
+            })
+            .await
+            .context("Failed to record run completion")
+    }
+
+    pub async fn fail_run(&self, run_id: i32, error: &str, counts: &RunCounts) -> Result<()> {
+        self.retry_policy()
+            .run("record run failure", || async {
+                sqlx::query(
+                    "UPDATE etl_watermarks \
+                     SET rows_processed = $2, rows_inserted = $3, rows_skipped = $4, \
+                         status = 'failed', error_message = $5, completed_at = now() \
+                     WHERE id = $1",
+                )
+                .bind(run_id)
+                .bind(counts.processed as i64)
+                .bind(counts.inserted as i64)
+                .bind(counts.skipped as i64)
+                .bind(error)
+                .execute(self.pool())
+                .await?;
+                Ok(())
+            })
+            .await
+            .context("Failed to record run failure")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(unique_key: i64, hour: u32) -> ServiceRequest {
+        ServiceRequest {
+            unique_key,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_watermark_covers_and_lookback() {
+        let watermark = Watermark::of(&record(10, 12));
+
+        assert!(watermark.covers(&record(10, 12)));
+        assert!(watermark.covers(&record(11, 11)));
+        assert!(!watermark.covers(&record(11, 12)));
+        assert!(!watermark.covers(&record(1, 13)));
+
+        let lookback = watermark.lookback(Duration::hours(2));
+        assert!(!lookback.covers(&record(5, 11)));
+        assert!(lookback.covers(&record(5, 10)));
+    }
+
+    #[test]
+    fn test_high_water_stops_below_failed_chunk() {
+        // Chunks finishing out of order: the newest commits before an older
+        // one rolls back
+        let mut high_water = HighWater::default();
+        high_water.add(Some(Watermark::of(&record(30, 15))), None);
+        high_water.add(None, Some(Watermark::of(&record(20, 10))));
+        high_water.add(Some(Watermark::of(&record(5, 8))), None);
+
+        let watermark = high_water.watermark().unwrap();
+        assert!(watermark.covers(&record(19, 10)));
+        assert!(!watermark.covers(&record(20, 10)));
+        assert!(!watermark.covers(&record(30, 15)));
+
+        // A failure inside the lookback window moves the watermark back
+        let mut resumed = HighWater::resuming_from(Some(Watermark::of(&record(40, 20))));
+        resumed.add(Some(Watermark::of(&record(41, 20))), None);
+        resumed.add(None, Some(Watermark::of(&record(35, 18))));
+        let watermark = resumed.watermark().unwrap();
+        assert!(watermark.covers(&record(34, 18)));
+        assert!(!watermark.covers(&record(35, 18)));
+
+        let mut quiet = HighWater::resuming_from(Some(Watermark::of(&record(40, 20))));
+        quiet.add(None, None);
+        assert_eq!(quiet.watermark(), Some(Watermark::of(&record(40, 20))));
+
+        let mut clean = HighWater::default();
+        clean.add(Some(Watermark::of(&record(30, 15))), None);
+        assert_eq!(clean.watermark(), Some(Watermark::of(&record(30, 15))));
+        assert_eq!(HighWater::default().watermark(), None);
+    }
+
+    #[test]
+    fn test_high_water_keeps_newest_loaded() {
+        let mut high_water = HighWater::default();
+        high_water.add(None, None);
+        assert_eq!(high_water.watermark(), None);
+
+        let records = [record(3, 9), record(2, 12), record(1, 12)];
+        for record in &records {
+            high_water.add(Some(Watermark::of(record)), None);
+        }
+        assert_eq!(high_water.watermark(), Some(Watermark::of(&records[1])));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a disposable PostgreSQL database at TEST_DATABASE_URL"]
+    async fn test_retried_run_start_inserts_one_row() {
+        let _lock = crate::db::schema::tests::SCHEMA_LOCK.lock().await;
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let db = Database::connect(&url).await.unwrap();
+        db.initialize_schema(crate::db::Partitioning::None)
+            .await
+            .unwrap();
+
+        let run_id = uuid::Uuid::new_v4();
+        let first = db
+            .insert_run(run_id, RunMode::Incremental, "a.csv")
+            .await
+            .unwrap();
+        let retried = db
+            .insert_run(run_id, RunMode::Incremental, "a.csv")
+            .await
+            .unwrap();
+
+        assert_eq!(first, retried);
+        let (rows,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM etl_watermarks WHERE run_id = $1")
+                .bind(run_id)
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(rows, 1);
+    }
+}
@@ -0,0 +1,111 @@
+// Derived analytic fields computed at transform time
+use chrono::{Datelike, Timelike};
+use chrono_tz::America::New_York;
+use chrono_tz::Tz;
+
+use crate::db::schema::ServiceRequest;
+use crate::geo::{encode_geohash, HexGrid};
+
+pub const DEFAULT_GEOHASH_PRECISION: usize = 8;
+/// Local timezone for NYC 311 data; day/hour buckets follow the city clock.
+pub const NYC_TIMEZONE: Tz = New_York;
+
+const SECONDS_PER_HOUR: f64 = 3600.0;
+
+/// Spatial grids each record with coordinates is assigned to.
+#[derive(Debug, Clone, Copy)]
+pub struct GridConfig {
+    pub geohash_precision: usize,
+    pub hex_grid: HexGrid,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            geohash_precision: DEFAULT_GEOHASH_PRECISION,
+            hex_grid: HexGrid::default(),
+        }
+    }
+}
+
+/// Fills the derived columns of `record` from its timestamps so analysts and
+/// materialized views can group on indexed columns instead of expressions.
+pub fn derive_fields(record: &mut ServiceRequest) {
+    let created_local = record.created_at.with_timezone(&NYC_TIMEZONE);
+    let day_of_week = created_local.weekday().number_from_monday() as i16;
+
+    record.created_local_date = Some(created_local.date_naive());
+    record.created_hour = Some(created_local.hour() as i16);
+    record.day_of_week = Some(day_of_week);
+    record.is_weekend = Some(day_of_week >= 6);
+    record.is_open = Some(record.closed_at.is_none());
+    record.resolution_hours = resolution_hours(record);
+}
+
+/// Hours between creation and closure, if the request is closed. Outlier
+/// detection uses it too, so its statistics match the stored column.
+pub(crate) fn resolution_hours(record: &ServiceRequest) -> Option<f64> {
+    record
+        .closed_at
+        .map(|closed| (closed - record.created_at).num_seconds() as f64 / SECONDS_PER_HOUR)
+}
+
+/// Assigns geohash and hex grid cells for hotspot aggregation without PostGIS.
+/// Records without both coordinates are left unassigned.
+pub fn assign_grid_cells(record: &mut ServiceRequest, grid: &GridConfig) {
+    let (Some(lat), Some(lon)) = (record.latitude, record.longitude) else {
+        record.geohash = None;
+        record.hex_cell = None;
+        return;
+    };
+
+    record.geohash = Some(encode_geohash(lat, lon, grid.geohash_precision));
+    record.hex_cell = Some(grid.hex_grid.cell_id(lat, lon));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn test_derive_fields_uses_nyc_local_time() {
+        // 2025-01-04 03:30 UTC is Friday 22:30 in New York (EST, UTC-5)
+        let mut record = ServiceRequest {
+            unique_key: 1,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 4, 3, 30, 0).unwrap(),
+            closed_at: Some(Utc.with_ymd_and_hms(2025, 1, 4, 9, 0, 0).unwrap()),
+            ..Default::default()
+        };
+
+        derive_fields(&mut record);
+
+        assert_eq!(
+            record.created_local_date,
+            NaiveDate::from_ymd_opt(2025, 1, 3)
+        );
+        assert_eq!(record.created_hour, Some(22));
+        assert_eq!(record.day_of_week, Some(5));
+        assert_eq!(record.is_weekend, Some(false));
+        assert_eq!(record.is_open, Some(false));
+        assert_eq!(record.resolution_hours, Some(5.5));
+    }
+
+    #[test]
+    fn test_derive_fields_open_request_during_dst() {
+        // 2025-07-05 16:00 UTC is Saturday 12:00 in New York (EDT, UTC-4)
+        let mut record = ServiceRequest {
+            unique_key: 2,
+            created_at: Utc.with_ymd_and_hms(2025, 7, 5, 16, 0, 0).unwrap(),
+            ..Default::default()
+        };
+
+        derive_fields(&mut record);
+
+        assert_eq!(record.created_hour, Some(12));
+        assert_eq!(record.day_of_week, Some(6));
+        assert_eq!(record.is_weekend, Some(true));
+        assert_eq!(record.is_open, Some(true));
+        assert_eq!(record.resolution_hours, None);
+    }
+}
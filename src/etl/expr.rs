@@ -0,0 +1,856 @@
+// Expression language for config-driven filters and derived columns
+//
+// Expressions are parsed and type-checked once, when configuration is
+// loaded, so a misspelled field or a comparison between incompatible types
+// fails the run before any data is read.
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use serde_json::json;
+
+use crate::db::schema::ServiceRequest;
+use crate::etl::derive::NYC_TIMEZONE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Number,
+    Text,
+    Timestamp,
+    Date,
+    /// Type of the `null` literal; compatible with every other type
+    Null,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::Bool => "bool",
+            Type::Number => "number",
+            Type::Text => "text",
+            Type::Timestamp => "timestamp",
+            Type::Date => "date",
+            Type::Null => "null",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    Timestamp(DateTime<Utc>),
+    Date(NaiveDate),
+}
+
+impl Value {
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => json!(b),
+            Value::Number(n) => json!(n),
+            Value::Text(s) => json!(s),
+            Value::Timestamp(ts) => json!(ts.to_rfc3339()),
+            Value::Date(d) => json!(d.to_string()),
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+            (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// `ServiceRequest` fields an expression may reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    UniqueKey,
+    CreatedAt,
+    ClosedAt,
+    Agency,
+    ComplaintType,
+    ComplaintCategory,
+    ComplaintSubcategory,
+    Descriptor,
+    ResolutionDescription,
+    Borough,
+    Latitude,
+    Longitude,
+    ResolutionHours,
+    CreatedLocalDate,
+    CreatedHour,
+    DayOfWeek,
+    IsOpen,
+    IsWeekend,
+    Geohash,
+    HexCell,
+    QualityFlags,
+}
+
+impl Field {
+    const ALL: &'static [(&'static str, Field, Type)] = &[
+        ("unique_key", Field::UniqueKey, Type::Number),
+        ("created_at", Field::CreatedAt, Type::Timestamp),
+        ("closed_at", Field::ClosedAt, Type::Timestamp),
+        ("agency", Field::Agency, Type::Text),
+        ("complaint_type", Field::ComplaintType, Type::Text),
+        ("complaint_category", Field::ComplaintCategory, Type::Text),
+        (
+            "complaint_subcategory",
+            Field::ComplaintSubcategory,
+            Type::Text,
+        ),
+        ("descriptor", Field::Descriptor, Type::Text),
+        (
+            "resolution_description",
+            Field::ResolutionDescription,
+            Type::Text,
+        ),
+        ("borough", Field::Borough, Type::Text),
+        ("latitude", Field::Latitude, Type::Number),
+        ("longitude", Field::Longitude, Type::Number),
+        ("resolution_hours", Field::ResolutionHours, Type::Number),
+        ("created_local_date", Field::CreatedLocalDate, Type::Date),
+        ("created_hour", Field::CreatedHour, Type::Number),
+        ("day_of_week", Field::DayOfWeek, Type::Number),
+        ("is_open", Field::IsOpen, Type::Bool),
+        ("is_weekend", Field::IsWeekend, Type::Bool),
+        ("geohash", Field::Geohash, Type::Text),
+        ("hex_cell", Field::HexCell, Type::Text),
+        ("quality_flags", Field::QualityFlags, Type::Number),
+    ];
+
+    fn lookup(name: &str) -> Option<(Field, Type)> {
+        Self::ALL
+            .iter()
+            .find(|(field_name, _, _)| *field_name == name)
+            .map(|&(_, field, ty)| (field, ty))
+    }
+
+    fn value(&self, record: &ServiceRequest) -> Value {
+        fn text(value: &Option<String>) -> Value {
+            value.clone().map_or(Value::Null, Value::Text)
+        }
+        fn number(value: Option<f64>) -> Value {
+            value.map_or(Value::Null, Value::Number)
+        }
+        fn boolean(value: Option<bool>) -> Value {
+            value.map_or(Value::Null, Value::Bool)
+        }
+
+        match self {
+            Field::UniqueKey => Value::Number(record.unique_key as f64),
+            Field::CreatedAt => Value::Timestamp(record.created_at),
+            Field::ClosedAt => record.closed_at.map_or(Value::Null, Value::Timestamp),
+            Field::Agency => text(&record.agency),
+            Field::ComplaintType => Value::Text(record.complaint_type.clone()),
+            Field::ComplaintCategory => text(&record.complaint_category),
+            Field::ComplaintSubcategory => text(&record.complaint_subcategory),
+            Field::Descriptor => text(&record.descriptor),
+            Field::ResolutionDescription => text(&record.resolution_description),
+            Field::Borough => text(&record.borough),
+            Field::Latitude => number(record.latitude),
+            Field::Longitude => number(record.longitude),
+            Field::ResolutionHours => number(record.resolution_hours),
+            Field::CreatedLocalDate => record.created_local_date.map_or(Value::Null, Value::Date),
+            Field::CreatedHour => number(record.created_hour.map(f64::from)),
+            Field::DayOfWeek => number(record.day_of_week.map(f64::from)),
+            Field::IsOpen => boolean(record.is_open),
+            Field::IsWeekend => boolean(record.is_weekend),
+            Field::Geohash => text(&record.geohash),
+            Field::HexCell => text(&record.hex_cell),
+            Field::QualityFlags => Value::Number(record.quality_flags.bits() as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn from_token(op: &str) -> Option<Self> {
+        Some(match op {
+            "||" => BinOp::Or,
+            "&&" => BinOp::And,
+            "==" => BinOp::Eq,
+            "!=" => BinOp::Ne,
+            "<" => BinOp::Lt,
+            "<=" => BinOp::Le,
+            ">" => BinOp::Gt,
+            ">=" => BinOp::Ge,
+            "~" => BinOp::Match,
+            "+" => BinOp::Add,
+            "-" => BinOp::Sub,
+            "*" => BinOp::Mul,
+            "/" => BinOp::Div,
+            _ => return None,
+        })
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 3,
+            BinOp::Match => 3,
+            BinOp::Add | BinOp::Sub => 4,
+            BinOp::Mul | BinOp::Div => 5,
+        }
+    }
+
+    fn is_comparison(&self) -> bool {
+        self.precedence() == 3
+    }
+}
+
+/// Type-checked expression tree.
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Value),
+    Field(Field),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Match(Box<Node>, Regex),
+    If(Box<Node>, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, record: &ServiceRequest) -> Value {
+        match self {
+            Node::Literal(value) => value.clone(),
+            Node::Field(field) => field.value(record),
+            Node::Not(inner) => Value::Bool(!inner.eval(record).is_true()),
+            Node::Neg(inner) => match inner.eval(record) {
+                Value::Number(n) => Value::Number(-n),
+                _ => Value::Null,
+            },
+            Node::Match(inner, regex) => match inner.eval(record) {
+                Value::Text(text) => Value::Bool(regex.is_match(&text)),
+                _ => Value::Bool(false),
+            },
+            Node::If(cond, then, otherwise) => {
+                if cond.eval(record).is_true() {
+                    then.eval(record)
+                } else {
+                    otherwise.eval(record)
+                }
+            }
+            Node::Binary(BinOp::Or, left, right) => {
+                Value::Bool(left.eval(record).is_true() || right.eval(record).is_true())
+            }
+            Node::Binary(BinOp::And, left, right) => {
+                Value::Bool(left.eval(record).is_true() && right.eval(record).is_true())
+            }
+            Node::Binary(op, left, right) => {
+                let (left, right) = (left.eval(record), right.eval(record));
+                match op {
+                    BinOp::Eq => Value::Bool(left == right),
+                    BinOp::Ne => Value::Bool(left != right),
+                    BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                        // Ordering against a missing value is never true
+                        let ordering = left.partial_cmp(&right);
+                        Value::Bool(ordering.is_some_and(|ord| match op {
+                            BinOp::Lt => ord.is_lt(),
+                            BinOp::Le => ord.is_le(),
+                            BinOp::Gt => ord.is_gt(),
+                            _ => ord.is_ge(),
+                        }))
+                    }
+                    _ => match (left, right) {
+                        (Value::Number(a), Value::Number(b)) => Value::Number(match op {
+                            BinOp::Add => a + b,
+                            BinOp::Sub => a - b,
+                            BinOp::Mul => a * b,
+                            _ => a / b,
+                        }),
+                        _ => Value::Null,
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// A compiled expression over [`ServiceRequest`] fields.
+///
+/// Supports field references, `'text'` / number / `true` / `false` / `null`
+/// literals, `||`, `&&`, `!`, comparisons, `~` (case-insensitive regex
+/// match), arithmetic, and `if <cond> then <a> else <b>`. Text literals
+/// compared with a timestamp or date field are parsed as dates; date-only and
+/// naive timestamps are read as NYC local time.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+    ty: Type,
+}
+
+impl Expression {
+    pub fn compile(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let ast = parser.expression(0)?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {} in expression: {}", token, source);
+        }
+
+        let (root, ty) = check(ast).with_context(|| format!("Invalid expression: {}", source))?;
+        Ok(Self {
+            source: source.trim().to_string(),
+            root,
+            ty,
+        })
+    }
+
+    /// Compiles an expression that must evaluate to a boolean.
+    pub fn compile_predicate(source: &str) -> Result<Self> {
+        let expression = Self::compile(source)?;
+        ensure!(
+            matches!(expression.ty, Type::Bool),
+            "Expression must be a bool, got {}: {}",
+            expression.ty,
+            source
+        );
+        Ok(expression)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+
+    pub fn eval(&self, record: &ServiceRequest) -> Value {
+        self.root.eval(record)
+    }
+
+    /// True when the expression evaluates to `true`; `null` counts as false.
+    pub fn matches(&self, record: &ServiceRequest) -> bool {
+        self.eval(record).is_true()
+    }
+}
+
+/// A named value computed per record and stored in `attributes`.
+#[derive(Debug, Clone)]
+pub struct DerivedColumn {
+    pub name: String,
+    pub expression: Expression,
+}
+
+impl DerivedColumn {
+    /// Parses a `;`-separated list of `name = expression` definitions.
+    pub fn parse_list(spec: &str) -> Result<Vec<Self>> {
+        let mut columns = Vec::new();
+        let mut names = HashSet::new();
+
+        for definition in split_outside_quotes(spec, ';') {
+            if definition.trim().is_empty() {
+                continue;
+            }
+            let (name, source) = definition
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected name = expression, got: {}", definition))?;
+            let name = name.trim();
+
+            ensure!(is_identifier(name), "Invalid derived column name: {}", name);
+            ensure!(
+                Field::lookup(name).is_none(),
+                "Derived column {} shadows a record field",
+                name
+            );
+            ensure!(
+                names.insert(name.to_string()),
+                "Duplicate derived column: {}",
+                name
+            );
+
+            columns.push(DerivedColumn {
+                name: name.to_string(),
+                expression: Expression::compile(source)?,
+            });
+        }
+
+        Ok(columns)
+    }
+}
+
+// --- Lexer -----------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Str(s) => write!(f, "string '{}'", s),
+            Token::Num(n) => write!(f, "number {}", n),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::LParen => f.write_str("'('"),
+            Token::RParen => f.write_str("')'"),
+        }
+    }
+}
+
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "~", "+", "-", "*", "/",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            chars.next();
+            tokens.push(if c == '(' {
+                Token::LParen
+            } else {
+                Token::RParen
+            });
+        } else if c == '\'' {
+            chars.next();
+            let mut literal = String::new();
+            loop {
+                match chars.next() {
+                    // A doubled quote inside a literal stands for one quote
+                    Some((_, '\'')) if chars.peek().is_some_and(|&(_, c)| c == '\'') => {
+                        chars.next();
+                        literal.push('\'');
+                    }
+                    Some((_, '\'')) => break,
+                    Some((_, c)) => literal.push(c),
+                    None => bail!("Unterminated string in expression: {}", source),
+                }
+            }
+            tokens.push(Token::Str(literal));
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = &source[start..end];
+            tokens.push(Token::Num(number.parse().with_context(|| {
+                format!("Invalid number {} in expression", number)
+            })?));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(source[start..end].to_string()));
+        } else {
+            let rest = &source[start..];
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| anyhow!("Unexpected character '{}' in expression: {}", c, source))?;
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// --- Parser ----------------------------------------------------------------
+
+/// Untyped syntax tree produced by the parser.
+#[derive(Debug, Clone)]
+enum Ast {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+    Null,
+    Ident(String),
+    Not(Box<Ast>),
+    Neg(Box<Ast>),
+    Binary(BinOp, Box<Ast>, Box<Ast>),
+    If(Box<Ast>, Box<Ast>, Box<Ast>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_binary(&self) -> Option<BinOp> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => BinOp::from_token(op),
+            _ => None,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        match self.next() {
+            Some(Token::Ident(ref word)) if word == keyword => Ok(()),
+            Some(token) => bail!("Expected '{}', found {}", keyword, token),
+            None => bail!("Expected '{}', found end of expression", keyword),
+        }
+    }
+
+    /// Precedence climbing; comparisons do not chain.
+    fn expression(&mut self, min_precedence: u8) -> Result<Ast> {
+        let mut left = self.unary()?;
+
+        while let Some(op) = self.peek_binary() {
+            if op.precedence() <= min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let right = self.expression(op.precedence())?;
+            if op.is_comparison() && self.peek_binary().is_some_and(|next| next.is_comparison()) {
+                bail!("Comparisons cannot be chained; use && to combine them");
+            }
+            left = Ast::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Ast> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Ast::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(Ast::Neg(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Ast> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Ast::Str(s)),
+            Some(Token::Num(n)) => Ok(Ast::Num(n)),
+            Some(Token::LParen) => {
+                let inner = self.expression(0)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => bail!("Expected ')'"),
+                }
+            }
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Ok(Ast::Bool(true)),
+                "false" => Ok(Ast::Bool(false)),
+                "null" => Ok(Ast::Null),
+                "if" => {
+                    let cond = self.expression(0)?;
+                    self.expect_keyword("then")?;
+                    let then = self.expression(0)?;
+                    self.expect_keyword("else")?;
+                    let otherwise = self.expression(0)?;
+                    Ok(Ast::If(Box::new(cond), Box::new(then), Box::new(otherwise)))
+                }
+                "then" | "else" => bail!("Unexpected '{}'", word),
+                _ => Ok(Ast::Ident(word)),
+            },
+            Some(token) => bail!("Unexpected {}", token),
+            None => bail!("Unexpected end of expression"),
+        }
+    }
+}
+
+// --- Type checker ----------------------------------------------------------
+
+fn check(ast: Ast) -> Result<(Node, Type)> {
+    Ok(match ast {
+        Ast::Str(s) => (Node::Literal(Value::Text(s)), Type::Text),
+        Ast::Num(n) => (Node::Literal(Value::Number(n)), Type::Number),
+        Ast::Bool(b) => (Node::Literal(Value::Bool(b)), Type::Bool),
+        Ast::Null => (Node::Literal(Value::Null), Type::Null),
+        Ast::Ident(name) => {
+            let (field, ty) =
+                Field::lookup(&name).ok_or_else(|| anyhow!("Unknown field: {}", name))?;
+            (Node::Field(field), ty)
+        }
+        Ast::Not(inner) => {
+            let (node, ty) = check(*inner)?;
+            expect_type(ty, Type::Bool, "'!'")?;
+            (Node::Not(Box::new(node)), Type::Bool)
+        }
+        Ast::Neg(inner) => {
+            let (node, ty) = check(*inner)?;
+            expect_type(ty, Type::Number, "'-'")?;
+            (Node::Neg(Box::new(node)), Type::Number)
+        }
+        Ast::If(cond, then, otherwise) => {
+            let (cond, cond_ty) = check(*cond)?;
+            expect_type(cond_ty, Type::Bool, "if condition")?;
+            let (then, then_ty) = check(*then)?;
+            let (otherwise, otherwise_ty) = check(*otherwise)?;
+            let ty = unify(then_ty, otherwise_ty).ok_or_else(|| {
+                anyhow!(
+                    "if branches have different types: {} and {}",
+                    then_ty,
+                    otherwise_ty
+                )
+            })?;
+            (
+                Node::If(Box::new(cond), Box::new(then), Box::new(otherwise)),
+                ty,
+            )
+        }
+        Ast::Binary(BinOp::Match, left, right) => {
+            let (left, left_ty) = check(*left)?;
+            expect_type(left_ty, Type::Text, "'~'")?;
+            let Ast::Str(pattern) = *right else {
+                bail!("Right-hand side of '~' must be a string literal");
+            };
+            let regex = Regex::new(&format!("(?i){}", pattern))
+                .with_context(|| format!("Invalid pattern: {}", pattern))?;
+            (Node::Match(Box::new(left), regex), Type::Bool)
+        }
+        Ast::Binary(op, left, right) => {
+            let (left, left_ty) = check(*left)?;
+            let (right, right_ty) = check(*right)?;
+            let (left, left_ty) = coerce_literal(left, left_ty, right_ty)?;
+            let (right, right_ty) = coerce_literal(right, right_ty, left_ty)?;
+
+            let ty = match op {
+                BinOp::Or | BinOp::And => {
+                    expect_type(left_ty, Type::Bool, "'||' / '&&'")?;
+                    expect_type(right_ty, Type::Bool, "'||' / '&&'")?;
+                    Type::Bool
+                }
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => {
+                    expect_type(left_ty, Type::Number, "arithmetic")?;
+                    expect_type(right_ty, Type::Number, "arithmetic")?;
+                    Type::Number
+                }
+                _ => {
+                    let operand = unify(left_ty, right_ty)
+                        .ok_or_else(|| anyhow!("Cannot compare {} with {}", left_ty, right_ty))?;
+                    if !matches!(op, BinOp::Eq | BinOp::Ne)
+                        && matches!(operand, Type::Bool | Type::Null)
+                    {
+                        bail!("Cannot order {} values", operand);
+                    }
+                    Type::Bool
+                }
+            };
+            (Node::Binary(op, Box::new(left), Box::new(right)), ty)
+        }
+    })
+}
+
+fn expect_type(actual: Type, expected: Type, context: &str) -> Result<()> {
+    ensure!(
+        actual == expected || actual == Type::Null,
+        "{} expects {}, got {}",
+        context,
+        expected,
+        actual
+    );
+    Ok(())
+}
+
+fn unify(a: Type, b: Type) -> Option<Type> {
+    match (a, b) {
+        (a, b) if a == b => Some(a),
+        (Type::Null, other) | (other, Type::Null) => Some(other),
+        _ => None,
+    }
+}
+
+/// Turns a text literal compared against a timestamp or date into one.
+fn coerce_literal(node: Node, ty: Type, other: Type) -> Result<(Node, Type)> {
+    let Node::Literal(Value::Text(ref text)) = node else {
+        return Ok((node, ty));
+    };
+
+    match other {
+        Type::Timestamp => Ok((
+            Node::Literal(Value::Timestamp(parse_timestamp(text)?)),
+            Type::Timestamp,
+        )),
+        Type::Date => {
+            let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .with_context(|| format!("Invalid date literal: {}", text))?;
+            Ok((Node::Literal(Value::Date(date)), Type::Date))
+        }
+        _ => Ok((node, ty)),
+    }
+}
+
+fn parse_timestamp(text: &str) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(text) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is valid"))
+        })
+        .with_context(|| format!("Invalid timestamp literal: {}", text))?;
+
+    NYC_TIMEZONE
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|ts| ts.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("Timestamp does not exist in NYC local time: {}", text))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn split_outside_quotes(spec: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in spec.char_indices() {
+        if c == '\'' {
+            in_quotes = !in_quotes;
+        } else if c == separator && !in_quotes {
+            parts.push(&spec[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&spec[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ServiceRequest {
+        ServiceRequest {
+            unique_key: 42,
+            created_at: Utc.with_ymd_and_hms(2021, 3, 1, 15, 0, 0).unwrap(),
+            complaint_type: "HEAT/HOT WATER".to_string(),
+            borough: Some("STATEN ISLAND".to_string()),
+            resolution_hours: Some(30.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_expression() {
+        let filter = Expression::compile_predicate(
+            "borough != 'STATEN ISLAND' || created_at >= '2020-01-01'",
+        )
+        .unwrap();
+        assert!(filter.matches(&record()));
+
+        let old = ServiceRequest {
+            created_at: Utc.with_ymd_and_hms(2019, 6, 1, 0, 0, 0).unwrap(),
+            ..record()
+        };
+        assert!(!filter.matches(&old));
+    }
+
+    #[test]
+    fn test_derived_column_expression() {
+        let columns = DerivedColumn::parse_list(
+            "priority = if complaint_type ~ 'heat' then 'high' else 'normal'; \
+             resolution_days = resolution_hours / 24",
+        )
+        .unwrap();
+
+        assert_eq!(columns[0].name, "priority");
+        assert_eq!(
+            columns[0].expression.eval(&record()),
+            Value::Text("high".into())
+        );
+        assert_eq!(columns[1].expression.ty(), Type::Number);
+        assert_eq!(columns[1].expression.eval(&record()), Value::Number(1.25));
+    }
+
+    #[test]
+    fn test_null_semantics() {
+        let record = record();
+        assert!(Expression::compile("closed_at == null")
+            .unwrap()
+            .matches(&record));
+        assert!(!Expression::compile("latitude > 40")
+            .unwrap()
+            .matches(&record));
+        assert!(Expression::compile("agency != 'NYPD'")
+            .unwrap()
+            .matches(&record));
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(Expression::compile("boro == 'BRONX'").is_err());
+        assert!(Expression::compile("borough > 3").is_err());
+        assert!(Expression::compile("created_at >= 'yesterday'").is_err());
+        assert!(Expression::compile("if is_open then 'open' else 1").is_err());
+        assert!(Expression::compile("1 < 2 < 3").is_err());
+        assert!(Expression::compile("borough ~ agency").is_err());
+        assert!(Expression::compile_predicate("resolution_hours * 2").is_err());
+        assert!(DerivedColumn::parse_list("borough = 'X'").is_err());
+        assert!(DerivedColumn::parse_list("a = 1; a = 2").is_err());
+    }
+}
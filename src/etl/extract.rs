@@ -3,10 +3,13 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, NaiveDateTime, Utc};
 use csv_async::AsyncReaderBuilder;
 use serde::Deserialize;
+use std::time::{Duration, Instant};
 use tokio::fs::File;
+use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 use tracing::{debug, info, warn};
 
+use crate::clean::QualityFlags;
 use crate::db::schema::ServiceRequest;
 
 #[derive(Debug, Deserialize)]
@@ -14,11 +17,15 @@ struct CsvRecord {
     unique_key: String,
     created_date: String,
     closed_date: Option<String>,
+    agency: Option<String>,
     complaint_type: String,
     descriptor: Option<String>,
+    resolution_description: Option<String>,
     borough: Option<String>,
     latitude: Option<String>,
     longitude: Option<String>,
+    x_coordinate_state_plane: Option<String>,
+    y_coordinate_state_plane: Option<String>,
 }
 
 impl CsvRecord {
@@ -30,7 +37,7 @@ impl CsvRecord {
             .context("Invalid unique_key")?;
 
         let created_at = parse_datetime(&self.created_date)?;
-        
+
         let closed_at = if let Some(ref closed) = self.closed_date {
             if !closed.trim().is_empty() {
                 Some(parse_datetime(closed)?)
@@ -53,19 +60,56 @@ impl CsvRecord {
             None
         };
 
+        let x_coordinate_state_plane = self
+            .x_coordinate_state_plane
+            .as_ref()
+            .and_then(|x| x.trim().parse::<f64>().ok());
+        let y_coordinate_state_plane = self
+            .y_coordinate_state_plane
+            .as_ref()
+            .and_then(|y| y.trim().parse::<f64>().ok());
+
         Ok(ServiceRequest {
             unique_key,
             created_at,
             closed_at,
+            agency: non_empty(&self.agency),
             complaint_type: self.complaint_type.trim().to_string(),
-            descriptor: self.descriptor.as_ref().map(|s| s.trim().to_string()),
-            borough: self.borough.as_ref().map(|s| s.trim().to_uppercase()),
+            complaint_category: None,
+            complaint_subcategory: None,
+            descriptor: non_empty(&self.descriptor),
+            resolution_description: non_empty(&self.resolution_description),
+            borough: non_empty(&self.borough).map(|s| s.to_uppercase()),
             latitude,
             longitude,
+            x_coordinate_state_plane,
+            y_coordinate_state_plane,
+            coordinate_repair: None,
+            resolution_hours: None,
+            created_local_date: None,
+            created_hour: None,
+            day_of_week: None,
+            is_open: None,
+            is_weekend: None,
+            geohash: None,
+            hex_cell: None,
+            quality_flags: QualityFlags::default(),
+            failed_rules: Vec::new(),
+            corrections: Vec::new(),
+            attributes: serde_json::Map::new(),
         })
     }
 }
 
+/// Trims an optional text column, treating blank values as missing.
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
 fn parse_datetime(date_str: &str) -> Result<DateTime<Utc>> {
     // Try parsing common formats
     let formats = vec![
@@ -84,6 +128,15 @@ fn parse_datetime(date_str: &str) -> Result<DateTime<Utc>> {
     Err(anyhow::anyhow!("Unable to parse date: {}", date_str))
 }
 
+/// What [`Extractor::extract_into`] read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractStats {
+    pub records: usize,
+    pub chunks: usize,
+    /// Time spent reading and parsing, not waiting for chunks to be taken
+    pub busy: Duration,
+}
+
 pub struct Extractor {
     chunk_size: usize,
 }
@@ -94,7 +147,30 @@ impl Extractor {
     }
 
     pub async fn extract(&self, input_path: &str) -> Result<Vec<Vec<ServiceRequest>>> {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let collect = async {
+            let mut all_chunks = Vec::new();
+            while let Some(chunk) = receiver.recv().await {
+                all_chunks.push(chunk);
+            }
+            all_chunks
+        };
+
+        let (stats, all_chunks) = tokio::join!(self.extract_into(input_path, sender), collect);
+        stats?;
+        Ok(all_chunks)
+    }
+
+    /// Streams the records of `input_path` into `chunks`, waiting whenever
+    /// the channel is full; stops early if the receiver is dropped.
+    pub async fn extract_into(
+        &self,
+        input_path: &str,
+        chunks: mpsc::Sender<Vec<ServiceRequest>>,
+    ) -> Result<ExtractStats> {
         info!("Starting CSV extraction from: {}", input_path);
+        let mut stats = ExtractStats::default();
+        let mut busy_since = Instant::now();
 
         let file = File::open(input_path)
             .await
@@ -104,32 +180,38 @@ impl Extractor {
             .has_headers(true)
             .create_deserializer(file);
 
-        let mut all_chunks = Vec::new();
         let mut current_chunk = Vec::new();
-        let mut total_read = 0;
         let mut total_errors = 0;
 
         let mut records = reader.deserialize::<CsvRecord>();
 
         while let Some(result) = records.next().await {
             match result {
-                Ok(csv_record) => {
-                    match csv_record.to_service_request() {
-                        Ok(service_request) => {
-                            current_chunk.push(service_request);
-                            total_read += 1;
-
-                            if current_chunk.len() >= self.chunk_size {
-                                debug!("Chunk complete with {} records", current_chunk.len());
-                                all_chunks.push(std::mem::take(&mut current_chunk));
+                Ok(csv_record) => match csv_record.to_service_request() {
+                    Ok(service_request) => {
+                        current_chunk.push(service_request);
+                        stats.records += 1;
+
+                        if current_chunk.len() >= self.chunk_size {
+                            debug!("Chunk complete with {} records", current_chunk.len());
+                            stats.chunks += 1;
+                            stats.busy += busy_since.elapsed();
+                            if chunks
+                                .send(std::mem::take(&mut current_chunk))
+                                .await
+                                .is_err()
+                            {
+                                debug!("Chunk receiver closed, stopping extraction");
+                                return Ok(stats);
                             }
-                        }
-                        Err(e) => {
-                            total_errors += 1;
-                            warn!("Failed to convert CSV record: {}", e);
+                            busy_since = Instant::now();
                         }
                     }
-                }
+                    Err(e) => {
+                        total_errors += 1;
+                        warn!("Failed to convert CSV record: {}", e);
+                    }
+                },
                 Err(e) => {
                     total_errors += 1;
                     warn!("Failed to parse CSV row: {}", e);
@@ -138,18 +220,19 @@ impl Extractor {
         }
 
         // Don't forget the last chunk
+        stats.busy += busy_since.elapsed();
         if !current_chunk.is_empty() {
-            all_chunks.push(current_chunk);
+            stats.chunks += 1;
+            // A closed receiver has stopped the run; nothing is left to do
+            let _ = chunks.send(current_chunk).await;
         }
 
         info!(
             "Extraction complete: {} records read, {} errors, {} chunks",
-            total_read,
-            total_errors,
-            all_chunks.len()
+            stats.records, total_errors, stats.chunks
         );
 
-        Ok(all_chunks)
+        Ok(stats)
     }
 }
 
@@ -1,26 +1,412 @@
 // Load phase - Bulk insert to PostgreSQL
-use anyhow::Result;
-use tracing::info;
+use anyhow::{bail, Result};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
 
-use crate::db::schema::{Database, ServiceRequest};
+use crate::clean::{QualityFlags, TemporalBounds};
+use crate::db::partition::PartitionMonth;
+use crate::db::refresh::{SwapStats, SwapThresholds, SHADOW_TABLE};
+use crate::db::schema::{
+    Database, LoadMode, LoadStats, ServiceRequest, StagingTables, SERVICE_REQUESTS_TABLE,
+};
+use crate::db::watermark::{HighWater, Watermark};
+use crate::etl::transform::TransformedChunk;
 
+/// Load failures a run tolerates before it is aborted; the defaults
+/// tolerate none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErrorBudget {
+    /// Records the database rejected, including every record of a failed chunk
+    pub max_failed_rows: u64,
+    /// Chunks with at least one failed record
+    pub max_failed_chunks: u64,
+}
+
+impl ErrorBudget {
+    pub fn check(&self, failed_rows: u64, failed_chunks: u64) -> Result<()> {
+        if failed_rows > self.max_failed_rows {
+            bail!(
+                "{} records failed to load, over the budget of {}",
+                failed_rows,
+                self.max_failed_rows
+            );
+        }
+        if failed_chunks > self.max_failed_chunks {
+            bail!(
+                "{} chunks failed to load, over the budget of {}",
+                failed_chunks,
+                self.max_failed_chunks
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Default number of chunks loaded concurrently.
+pub const DEFAULT_LOADERS: usize = 2;
+
+/// Outcome of loading one chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkLoad {
+    pub stats: LoadStats,
+    /// Newest record of the chunk, if it was committed
+    pub high_water: Option<Watermark>,
+    /// Oldest record of the chunk, if any of its records failed
+    pub failed_from: Option<Watermark>,
+}
+
+/// A chunk after the load phase, in the order chunks finish loading.
+#[derive(Debug)]
+pub struct LoadedChunk {
+    /// Position of the chunk in the input, starting at 1
+    pub number: usize,
+    /// Records in the chunk before the transform
+    pub extracted: usize,
+    /// Records left after the transform
+    pub kept: usize,
+    /// `None` in a dry run
+    pub load: Option<ChunkLoad>,
+    /// Time spent loading the chunk
+    pub elapsed: Duration,
+}
+
+impl LoadedChunk {
+    /// A transformed chunk that was not loaded, as in a dry run.
+    pub fn unloaded(chunk: &TransformedChunk) -> Self {
+        Self {
+            number: chunk.number,
+            extracted: chunk.extracted,
+            kept: chunk.records.len(),
+            load: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+/// Newest of `records` with a `created_at` inside `bounds`. Quarantined
+/// records are left out by the caller: they are not in the target table, and
+/// an implausible timestamp is often why they were held back.
+fn high_water(records: &[ServiceRequest], bounds: Option<TemporalBounds>) -> Option<Watermark> {
+    records
+        .iter()
+        .filter(|record| bounds.is_none_or(|bounds| bounds.contains(record.created_at)))
+        .map(Watermark::of)
+        .max()
+}
+
+/// Keeps the run-wide state of the load phase and hands out the
+/// [`LoadWorker`]s that load its chunks.
+///
+/// Each worker loads through its own pair of unlogged staging tables,
+/// created by [`worker`](Self::worker); call [`cleanup`](Self::cleanup) once
+/// the run ends, whether it succeeded or not, to drop them.
+///
+/// In a full refresh, chunks go to a shadow table instead of
+/// service_requests; [`finish`](Self::finish) swaps it in after the last
+/// chunk, and `cleanup` drops it if that never happened.
 pub struct Loader {
     db: Database,
+    mode: LoadMode,
+    /// Records outside these never move the watermark
+    bounds: Option<TemporalBounds>,
+    budget: ErrorBudget,
+    /// Set for a full refresh
+    swap_thresholds: Option<SwapThresholds>,
+    shadow_created: bool,
+    staging: Vec<StagingTables>,
+    /// Months the target has partitions for, shared by the workers; `None`
+    /// while no worker exists or if the target is not partitioned
+    partitions: Option<Arc<Mutex<BTreeSet<PartitionMonth>>>>,
+    totals: LoadStats,
+    failed_chunks: u64,
+    high_water: HighWater,
 }
 
 impl Loader {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self {
+            db,
+            mode: LoadMode::default(),
+            bounds: None,
+            budget: ErrorBudget::default(),
+            swap_thresholds: None,
+            shadow_created: false,
+            staging: Vec::new(),
+            partitions: None,
+            totals: LoadStats::default(),
+            failed_chunks: 0,
+            high_water: HighWater::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: LoadMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Keeps records with an implausible `created_at` from moving the
+    /// watermark, even when they are loaded.
+    /// Starts the high-water mark at the watermark an incremental run
+    /// resumed from.
+    pub fn with_previous_watermark(mut self, previous: Option<Watermark>) -> Self {
+        self.high_water = HighWater::resuming_from(previous);
+        self
+    }
+
+    pub fn with_temporal_bounds(mut self, bounds: TemporalBounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    pub fn with_error_budget(mut self, budget: ErrorBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Replaces service_requests with this run's records once they pass
+    /// `thresholds`, instead of loading into it.
+    pub fn with_full_refresh(mut self, thresholds: SwapThresholds) -> Self {
+        self.swap_thresholds = Some(thresholds);
+        self
+    }
+
+    pub fn totals(&self) -> &LoadStats {
+        &self.totals
+    }
+
+    pub fn failed_chunks(&self) -> u64 {
+        self.failed_chunks
+    }
+
+    /// Newest record loaded by a committed chunk (or the previous watermark),
+    /// but below every record of a chunk with failures, so the next
+    /// incremental run loads those again.
+    pub fn high_water(&self) -> Option<Watermark> {
+        self.high_water.watermark()
     }
 
-    pub async fn load(&self, records: Vec<ServiceRequest>) -> Result<u64> {
+    /// Creates a worker with staging tables of its own, and the shadow
+    /// table of a full refresh if this is the first.
+    pub async fn worker(&mut self) -> Result<LoadWorker> {
+        let target = self.target().await?;
+        if self.partitions.is_none() {
+            self.partitions = self
+                .db
+                .partition_months(target)
+                .await?
+                .map(|months| Arc::new(Mutex::new(months)));
+        }
+        let staging = self.db.create_staging_tables().await?;
+        self.staging.push(staging.clone());
+
+        Ok(LoadWorker {
+            db: self.db.clone(),
+            target,
+            staging,
+            mode: self.mode,
+            bounds: self.bounds,
+            partitions: self.partitions.clone(),
+        })
+    }
+
+    /// Adds a loaded chunk to the run's totals; fails once the run's
+    /// failures exceed the error budget.
+    pub fn record(&mut self, load: &ChunkLoad) -> Result<()> {
+        if load.stats.failed > 0 {
+            self.failed_chunks += 1;
+        }
+        self.totals.add(&load.stats);
+        self.high_water.add(load.high_water, load.failed_from);
+        self.budget.check(self.totals.failed, self.failed_chunks)
+    }
+
+    /// Completes the load after the last chunk; in a full refresh this
+    /// swaps the shadow table in and returns its row counts.
+    pub async fn finish(&mut self) -> Result<Option<SwapStats>> {
+        let Some(thresholds) = self.swap_thresholds else {
+            return Ok(None);
+        };
+
+        self.target().await?;
+        let stats = self.db.swap_in_shadow_table(&thresholds).await?;
+        self.shadow_created = false;
+        Ok(Some(stats))
+    }
+
+    /// Drops the workers' staging tables and any shadow table that was not
+    /// swapped in, leaving service_requests as it was.
+    pub async fn cleanup(&mut self) -> Result<()> {
+        while let Some(staging) = self.staging.pop() {
+            self.db.drop_staging_tables(&staging).await?;
+        }
+        if self.shadow_created {
+            self.db.drop_shadow_table().await?;
+            self.shadow_created = false;
+        }
+        Ok(())
+    }
+
+    /// Table chunks are merged into, creating the shadow table on first use.
+    async fn target(&mut self) -> Result<&'static str> {
+        if self.swap_thresholds.is_none() {
+            return Ok(SERVICE_REQUESTS_TABLE);
+        }
+        if !self.shadow_created {
+            self.db.create_shadow_table().await?;
+            self.shadow_created = true;
+        }
+        Ok(SHADOW_TABLE)
+    }
+}
+
+/// Loads chunks through one pair of staging tables, one chunk at a time.
+pub struct LoadWorker {
+    db: Database,
+    target: &'static str,
+    staging: StagingTables,
+    mode: LoadMode,
+    bounds: Option<TemporalBounds>,
+    partitions: Option<Arc<Mutex<BTreeSet<PartitionMonth>>>>,
+}
+
+impl LoadWorker {
+    /// Loads one chunk in a single transaction, creating the partitions it
+    /// needs first if the target is partitioned. A chunk that cannot be
+    /// committed is rolled back and all of its records count as failed.
+    pub async fn load(&self, records: Vec<ServiceRequest>) -> ChunkLoad {
         info!("Loading {} records to database", records.len());
 
-        let inserted = self.db.bulk_insert(&records).await?;
+        let (quarantined, records): (Vec<_>, Vec<_>) = records
+            .into_iter()
+            .partition(|record| record.quality_flags.contains(QualityFlags::QUARANTINED));
 
-        info!("Successfully loaded {} records", inserted);
+        let mut load = match self.load_partitioned(&records, &quarantined).await {
+            Ok(stats) => ChunkLoad {
+                stats,
+                high_water: high_water(&records, self.bounds),
+                failed_from: None,
+            },
+            Err(e) => {
+                warn!("Chunk rolled back: {:#}", e);
+                ChunkLoad {
+                    stats: LoadStats {
+                        failed: (records.len() + quarantined.len()) as u64,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            }
+        };
+        if load.stats.failed > 0 {
+            load.failed_from = records.iter().chain(&quarantined).map(Watermark::of).min();
+        }
 
-        Ok(inserted)
+        let stats = &load.stats;
+        if stats.quarantined > 0 {
+            info!("Quarantined {} records", stats.quarantined);
+        }
+        info!(
+            inserted = stats.inserted,
+            updated = stats.updated,
+            unchanged = stats.unchanged,
+            failed = stats.failed,
+            "Loaded chunk"
+        );
+
+        load
+    }
+
+    async fn load_partitioned(
+        &self,
+        records: &[ServiceRequest],
+        quarantined: &[ServiceRequest],
+    ) -> Result<LoadStats> {
+        if let Some(ref partitions) = self.partitions {
+            let months: BTreeSet<_> = records
+                .iter()
+                .map(|record| PartitionMonth::of(record.created_at))
+                .collect();
+            // Held while creating, so two workers never create the same one
+            let mut known = partitions.lock().await;
+            let missing: Vec<_> = months.difference(&known).copied().collect();
+            if !missing.is_empty() {
+                self.db.create_partitions(self.target, &missing).await?;
+                known.extend(missing);
+            }
+        }
+
+        self.db
+            .load_chunk(self.target, &self.staging, records, quarantined, self.mode)
+            .await
+    }
+
+    /// Loads chunks taken from `chunks`, shared with the other workers,
+    /// until it is closed and drained, sending each to `loaded`. Stops
+    /// early if `loaded` is closed.
+    pub async fn run(
+        self,
+        chunks: Arc<Mutex<mpsc::Receiver<TransformedChunk>>>,
+        loaded: mpsc::Sender<LoadedChunk>,
+    ) {
+        loop {
+            let Some(chunk) = chunks.lock().await.recv().await else {
+                return;
+            };
+
+            let mut done = LoadedChunk::unloaded(&chunk);
+            let started = Instant::now();
+            done.load = Some(self.load(chunk.records).await);
+            done.elapsed = started.elapsed();
+
+            if loaded.send(done).await.is_err() {
+                return;
+            }
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn test_error_budget() {
+        let strict = ErrorBudget::default();
+        assert!(strict.check(0, 0).is_ok());
+        assert!(strict.check(1, 1).is_err());
+
+        let budget = ErrorBudget {
+            max_failed_rows: 10,
+            max_failed_chunks: 2,
+        };
+        assert!(budget.check(10, 2).is_ok());
+        assert!(budget.check(11, 1).is_err());
+        assert!(budget.check(3, 3).is_err());
+    }
+
+    #[test]
+    fn test_high_water_ignores_implausible_timestamps() {
+        let record = |unique_key, year| ServiceRequest {
+            unique_key,
+            created_at: Utc.with_ymd_and_hms(year, 6, 1, 0, 0, 0).unwrap(),
+            ..Default::default()
+        };
+        let bounds = TemporalBounds::new(
+            NaiveDate::from_ymd_opt(2010, 1, 1).unwrap(),
+            Duration::hours(24),
+            Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap(),
+        );
+        let records = [record(1, 2025), record(2, 2099), record(3, 1900)];
+
+        assert_eq!(
+            high_water(&records, Some(bounds)),
+            Some(Watermark::of(&records[0]))
+        );
+        assert_eq!(high_water(&records, None), Some(Watermark::of(&records[1])));
+        assert_eq!(high_water(&records[1..], Some(bounds)), None);
+    }
+}
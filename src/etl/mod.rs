@@ -1,9 +1,15 @@
 // ETL module - Extract, Transform, Load pipeline
+pub mod derive;
+pub mod expr;
 pub mod extract;
 pub mod load;
+pub mod stage;
 pub mod transform;
 
 // Re-exports for convenience
+pub use derive::*;
+pub use expr::*;
 pub use extract::*;
 pub use load::*;
+pub use stage::*;
 pub use transform::*;
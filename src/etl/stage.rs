@@ -0,0 +1,696 @@
+// Transform stages - composable steps run in order by a Pipeline
+use anyhow::Result;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use tracing::debug;
+
+use crate::clean::{
+    canonical_key, repair_coordinates, FieldCorrectors, QualityFlags, Redactor, ResolutionOutliers,
+    Rule, RuleContext, RulePolicies, RulePolicy, Sentinels, Taxonomy, TextNormalizer, Validator,
+};
+use crate::db::schema::ServiceRequest;
+use crate::db::watermark::Watermark;
+use crate::etl::derive::{assign_grid_cells, derive_fields, resolution_hours, GridConfig};
+use crate::etl::expr::{DerivedColumn, Expression};
+use crate::etl::transform::TransformSummary;
+
+/// One step of the transform phase.
+///
+/// A stage receives each chunk after the stages before it, and may rewrite,
+/// drop or enrich records. Stages are run for every chunk of a run, so any
+/// state they keep (and anything they add to `summary`) is run-wide.
+///
+/// Stages that keep no state between chunks can implement [`Stage::fork`] so
+/// a multi-worker [`Pipeline`](crate::etl::Pipeline) runs them on several
+/// chunks at once; all others see every chunk themselves, in input order.
+pub trait Stage: Send {
+    /// Name used to position other stages relative to this one and in logs.
+    fn name(&self) -> &str;
+
+    fn process(
+        &mut self,
+        records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>>;
+
+    /// Independent copy for another transform worker, or `None` if the
+    /// stage carries state from one chunk to the next.
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        None
+    }
+}
+
+/// Drops records whose `unique_key` was already seen earlier in the run.
+#[derive(Debug, Default)]
+pub struct DedupStage {
+    seen_keys: HashSet<i64>,
+}
+
+impl Stage for DedupStage {
+    fn name(&self) -> &str {
+        "dedup"
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        _summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        records.retain(|record| self.seen_keys.insert(record.unique_key));
+        Ok(records)
+    }
+}
+
+/// Drops records an earlier run already loaded, i.e. those at or below the
+/// incremental watermark.
+#[derive(Debug, Clone)]
+pub struct WatermarkStage {
+    watermark: Watermark,
+}
+
+impl WatermarkStage {
+    pub fn new(watermark: Watermark) -> Self {
+        Self { watermark }
+    }
+}
+
+impl Stage for WatermarkStage {
+    fn name(&self) -> &str {
+        "watermark"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        _summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        records.retain(|record| !self.watermark.covers(record));
+        Ok(records)
+    }
+}
+
+/// Normalizes Unicode, whitespace and casing of text fields so invisible
+/// differences do not split groups.
+#[derive(Debug, Clone, Default)]
+pub struct NormalizeStage {
+    normalizer: TextNormalizer,
+}
+
+impl NormalizeStage {
+    pub fn new(normalizer: TextNormalizer) -> Self {
+        Self { normalizer }
+    }
+}
+
+impl Stage for NormalizeStage {
+    fn name(&self) -> &str {
+        "normalize"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            self.normalizer.apply(record, |field| {
+                *summary.text_normalized.entry(field.as_str()).or_insert(0) += 1;
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// Cuts text fields to their maximum length. Runs after [`RedactStage`] so
+/// PII is scrubbed while it is still whole.
+#[derive(Debug, Clone, Default)]
+pub struct TruncateStage {
+    normalizer: TextNormalizer,
+}
+
+impl TruncateStage {
+    pub fn new(normalizer: TextNormalizer) -> Self {
+        Self { normalizer }
+    }
+}
+
+impl Stage for TruncateStage {
+    fn name(&self) -> &str {
+        "truncate"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            self.normalizer.apply_max_length(record, |field| {
+                *summary.text_truncated.entry(field.as_str()).or_insert(0) += 1;
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// Maps placeholder values such as "Unspecified" to NULL before anything
+/// validates or corrects them.
+#[derive(Debug, Clone, Default)]
+pub struct SentinelStage {
+    sentinels: Sentinels,
+}
+
+impl SentinelStage {
+    pub fn new(sentinels: Sentinels) -> Self {
+        Self { sentinels }
+    }
+}
+
+impl Stage for SentinelStage {
+    fn name(&self) -> &str {
+        "sentinels"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        let nulled = &mut summary.sentinels_nulled;
+        for record in records.iter_mut() {
+            self.sentinels.apply(record, |field| {
+                *nulled.entry(field.as_str()).or_insert(0) += 1;
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// Scrubs PII from free-text fields before anything else reads them.
+#[derive(Debug, Clone, Default)]
+pub struct RedactStage {
+    redactor: Redactor,
+}
+
+impl RedactStage {
+    pub fn new(redactor: Redactor) -> Self {
+        Self { redactor }
+    }
+}
+
+impl Stage for RedactStage {
+    fn name(&self) -> &str {
+        "redact"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        if self.redactor.is_empty() {
+            return Ok(records);
+        }
+
+        let redactions = &mut summary.redactions;
+        for record in records.iter_mut() {
+            for field in [&mut record.descriptor, &mut record.resolution_description] {
+                let Some(text) = field.as_deref() else {
+                    continue;
+                };
+                let redacted = self.redactor.redact(text, |kind| {
+                    *redactions.entry(kind.as_str()).or_insert(0) += 1;
+                });
+                if let Cow::Owned(redacted) = redacted {
+                    *field = Some(redacted);
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+/// Repairs misspelled borough, agency and complaint type values.
+#[derive(Debug, Clone)]
+pub struct CorrectStage {
+    correctors: FieldCorrectors,
+}
+
+impl CorrectStage {
+    pub fn new(correctors: FieldCorrectors) -> Self {
+        Self { correctors }
+    }
+}
+
+impl Stage for CorrectStage {
+    fn name(&self) -> &str {
+        "correct"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        let correctors = &self.correctors;
+
+        for record in records.iter_mut() {
+            let mut applied = Vec::new();
+
+            if let Some(ref borough) = record.borough {
+                if let Some(correction) = correctors.borough.correct(borough) {
+                    record.borough = Some(correction.corrected.clone());
+                    record.quality_flags.insert(QualityFlags::BOROUGH_INFERRED);
+                    applied.push(correction);
+                }
+            }
+
+            if let Some(ref agency) = record.agency {
+                if let Some(correction) = correctors.agency.correct(agency) {
+                    record.agency = Some(correction.corrected.clone());
+                    record.quality_flags.insert(QualityFlags::AGENCY_CORRECTED);
+                    applied.push(correction);
+                }
+            }
+
+            if let Some(ref corrector) = correctors.complaint_type {
+                if let Some(correction) = corrector.correct(&record.complaint_type) {
+                    record.complaint_type = correction.corrected.clone();
+                    record
+                        .quality_flags
+                        .insert(QualityFlags::COMPLAINT_TYPE_CORRECTED);
+                    applied.push(correction);
+                }
+            }
+
+            for correction in &applied {
+                debug!(
+                    unique_key = record.unique_key,
+                    field = %correction.field,
+                    original = %correction.original,
+                    corrected = %correction.corrected,
+                    confidence = correction.confidence,
+                    "Corrected categorical value"
+                );
+                *summary
+                    .corrections
+                    .entry(correction.field.clone())
+                    .or_insert(0) += 1;
+            }
+            record.corrections.extend(applied);
+        }
+
+        Ok(records)
+    }
+}
+
+/// Fixes swapped and placeholder coordinates, falling back to State Plane.
+#[derive(Debug, Clone, Default)]
+pub struct CoordinateRepairStage {
+    validator: Validator,
+}
+
+impl Stage for CoordinateRepairStage {
+    fn name(&self) -> &str {
+        "repair_coordinates"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            if let Some(repair) = repair_coordinates(record, &self.validator) {
+                record.coordinate_repair = Some(repair.as_str().to_string());
+                record
+                    .quality_flags
+                    .insert(QualityFlags::COORDINATES_REPAIRED);
+                *summary
+                    .coordinate_repairs
+                    .entry(repair.as_str().to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+/// Checks every [`Rule`], applying each rule's failure policy.
+#[derive(Debug, Clone, Default)]
+pub struct ValidateStage {
+    validator: Validator,
+    policies: RulePolicies,
+    outliers: ResolutionOutliers,
+}
+
+impl ValidateStage {
+    pub fn new(policies: RulePolicies) -> Self {
+        Self {
+            validator: Validator::new(),
+            policies,
+            outliers: ResolutionOutliers::default(),
+        }
+    }
+
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub fn with_outliers(mut self, outliers: ResolutionOutliers) -> Self {
+        self.outliers = outliers;
+        self
+    }
+
+    /// Adds resolution times to the run-wide outlier statistics.
+    fn observe_resolutions<'a>(&mut self, records: impl Iterator<Item = &'a ServiceRequest>) {
+        for record in records {
+            if let Some(hours) = resolution_hours(record) {
+                self.outliers.observe(&record.complaint_type, hours);
+            }
+        }
+    }
+
+    /// Applies the policy of each rule in `rules` that `record` fails.
+    /// Returns `None` if the record should be dropped, otherwise whether it
+    /// passed every rule. Evaluation stops at the first rule whose policy
+    /// rejects.
+    fn apply_rules(
+        &self,
+        rules: impl IntoIterator<Item = Rule>,
+        record: &mut ServiceRequest,
+        summary: &mut TransformSummary,
+    ) -> Option<bool> {
+        let context = RuleContext {
+            validator: &self.validator,
+            outliers: &self.outliers,
+        };
+
+        let mut passed = true;
+        for rule in rules {
+            if rule.check(&context, record) {
+                continue;
+            }
+            passed = false;
+
+            let policy = self.policies.policy(rule);
+            summary
+                .rule_outcomes
+                .entry(rule)
+                .or_default()
+                .record(policy);
+
+            match policy {
+                RulePolicy::Reject => return None,
+                RulePolicy::Nullify => {
+                    rule.nullify(record);
+                    record.quality_flags.insert(QualityFlags::FIELD_NULLIFIED);
+                }
+                RulePolicy::Flag => {
+                    if rule.constrained_by_schema() {
+                        rule.nullify(record);
+                        record.quality_flags.insert(QualityFlags::FIELD_NULLIFIED);
+                    }
+                    record.failed_rules.push(rule.as_str().to_string());
+                    record.quality_flags.insert(QualityFlags::RULE_FLAGGED);
+                }
+                RulePolicy::Quarantine => {
+                    record.failed_rules.push(rule.as_str().to_string());
+                    record.quality_flags.insert(QualityFlags::QUARANTINED);
+                }
+            }
+        }
+
+        Some(passed)
+    }
+}
+
+impl Stage for ValidateStage {
+    fn name(&self) -> &str {
+        "validate"
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        // Outlier statistics only take records that pass every other rule,
+        // so rejected, quarantined or repaired records cannot skew them. They
+        // still build up in chunk order: a chunk is judged against itself and
+        // the chunks before it, so early chunks see fewer samples.
+        let other_rules = || {
+            Rule::ALL
+                .iter()
+                .copied()
+                .filter(|&rule| rule != Rule::ResolutionOutlier)
+        };
+        let mut passed = Vec::with_capacity(records.len());
+        records.retain_mut(
+            |record| match self.apply_rules(other_rules(), record, summary) {
+                Some(clean) => {
+                    passed.push(clean);
+                    true
+                }
+                None => false,
+            },
+        );
+
+        self.observe_resolutions(
+            records
+                .iter()
+                .zip(&passed)
+                .filter(|(_, &clean)| clean)
+                .map(|(record, _)| record),
+        );
+        records.retain_mut(|record| {
+            self.apply_rules([Rule::ResolutionOutlier], record, summary)
+                .is_some()
+        });
+        Ok(records)
+    }
+}
+
+/// Maps complaint types onto the configured taxonomy.
+#[derive(Debug, Clone)]
+pub struct ClassifyStage {
+    taxonomy: Taxonomy,
+}
+
+impl ClassifyStage {
+    pub fn new(taxonomy: Taxonomy) -> Self {
+        Self { taxonomy }
+    }
+}
+
+impl Stage for ClassifyStage {
+    fn name(&self) -> &str {
+        "classify"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            match self
+                .taxonomy
+                .classify(&record.complaint_type, record.descriptor.as_deref())
+            {
+                Some(classification) => {
+                    record.complaint_category = Some(classification.category.clone());
+                    record.complaint_subcategory = classification.subcategory.clone();
+                }
+                None => {
+                    record
+                        .quality_flags
+                        .insert(QualityFlags::UNMAPPED_COMPLAINT_TYPE);
+                    *summary
+                        .unmapped_complaint_types
+                        .entry(canonical_key(&record.complaint_type))
+                        .or_insert(0) += 1;
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+/// Computes derived time fields and spatial grid cells.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeriveStage {
+    grid: GridConfig,
+}
+
+impl DeriveStage {
+    pub fn new(grid: GridConfig) -> Self {
+        Self { grid }
+    }
+}
+
+impl Stage for DeriveStage {
+    fn name(&self) -> &str {
+        "derive"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(*self))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        _summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            derive_fields(record);
+            assign_grid_cells(record, &self.grid);
+        }
+        Ok(records)
+    }
+}
+
+/// Sets the remaining quality flags and tallies them; runs last so flags set
+/// by every earlier stage are counted.
+#[derive(Debug, Clone, Default)]
+pub struct QualityStage;
+
+impl Stage for QualityStage {
+    fn name(&self) -> &str {
+        "assess_quality"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            record.quality_flags.set(
+                QualityFlags::MISSING_CLOSED_DATE,
+                record.closed_at.is_none(),
+            );
+            record.quality_flags.set(
+                QualityFlags::MISSING_COORDINATES,
+                record.latitude.is_none() || record.longitude.is_none(),
+            );
+
+            if record.quality_flags.is_pristine() {
+                summary.pristine_records += 1;
+            }
+            for name in record.quality_flags.names() {
+                *summary.quality_flags.entry(name).or_insert(0) += 1;
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+/// Keeps only records matching a configured predicate.
+#[derive(Debug, Clone)]
+pub struct FilterStage {
+    predicate: Expression,
+}
+
+impl FilterStage {
+    pub fn new(predicate: Expression) -> Self {
+        Self { predicate }
+    }
+}
+
+impl Stage for FilterStage {
+    fn name(&self) -> &str {
+        "filter"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        _summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        records.retain(|record| self.predicate.matches(record));
+        Ok(records)
+    }
+}
+
+/// Evaluates configured expressions into each record's `attributes`.
+#[derive(Debug, Clone)]
+pub struct DeriveColumnsStage {
+    columns: Vec<DerivedColumn>,
+}
+
+impl DeriveColumnsStage {
+    pub fn new(columns: Vec<DerivedColumn>) -> Self {
+        Self { columns }
+    }
+}
+
+impl Stage for DeriveColumnsStage {
+    fn name(&self) -> &str {
+        "derive_columns"
+    }
+
+    fn fork(&self) -> Option<Box<dyn Stage>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn process(
+        &mut self,
+        mut records: Vec<ServiceRequest>,
+        _summary: &mut TransformSummary,
+    ) -> Result<Vec<ServiceRequest>> {
+        for record in records.iter_mut() {
+            for column in &self.columns {
+                let value = column.expression.eval(record).to_json();
+                record.attributes.insert(column.name.clone(), value);
+            }
+        }
+        Ok(records)
+    }
+}
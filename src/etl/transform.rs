@@ -1,87 +1,835 @@
 // Transform phase - Data cleaning, validation, and deduplication
-use anyhow::Result;
-use std::collections::HashSet;
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tracing::{debug, info};
 
-use crate::clean::Validator;
+use crate::clean::{
+    FieldCorrectors, Redactor, ResolutionOutliers, Rule, RuleOutcomes, RulePolicies, Sentinels,
+    Taxonomy, TextNormalizer, Validator,
+};
 use crate::db::schema::ServiceRequest;
+use crate::etl::derive::GridConfig;
+use crate::etl::stage::{
+    ClassifyStage, CoordinateRepairStage, CorrectStage, DedupStage, DeriveStage, NormalizeStage,
+    QualityStage, RedactStage, SentinelStage, Stage, TruncateStage, ValidateStage,
+};
 
-pub struct Transformer {
-    validator: Validator,
+/// Run-wide statistics accumulated across every chunk passed to a [`Pipeline`].
+#[derive(Debug, Clone, Default)]
+pub struct TransformSummary {
+    /// Records dropped by each stage, keyed by stage name
+    pub removed_by_stage: BTreeMap<String, u64>,
+    /// Text values changed by normalization, keyed by field name
+    pub text_normalized: BTreeMap<&'static str, u64>,
+    /// Text values cut to their maximum length, keyed by field name
+    pub text_truncated: BTreeMap<&'static str, u64>,
+    /// Placeholder values mapped to NULL, keyed by field name
+    pub sentinels_nulled: BTreeMap<&'static str, u64>,
+    /// Complaint types with no taxonomy entry, keyed by canonical form
+    pub unmapped_complaint_types: BTreeMap<String, u64>,
+    /// Categorical corrections applied, keyed by field name
+    pub corrections: BTreeMap<String, u64>,
+    /// Coordinate repairs applied, keyed by repair kind
+    pub coordinate_repairs: BTreeMap<String, u64>,
+    /// Validation failures per rule, split by the policy outcome
+    pub rule_outcomes: BTreeMap<Rule, RuleOutcomes>,
+    /// Output records carrying each quality flag, keyed by flag name
+    pub quality_flags: BTreeMap<&'static str, u64>,
+    /// Output records with no quality flags set
+    pub pristine_records: u64,
+    /// PII matches replaced in free-text fields, keyed by detector
+    pub redactions: BTreeMap<&'static str, u64>,
 }
 
-impl Transformer {
-    pub fn new() -> Self {
+impl TransformSummary {
+    /// Adds the counts of another summary, e.g. one kept by a transform worker.
+    pub fn merge(&mut self, other: TransformSummary) {
+        add_counts(&mut self.removed_by_stage, other.removed_by_stage);
+        add_counts(&mut self.text_normalized, other.text_normalized);
+        add_counts(&mut self.text_truncated, other.text_truncated);
+        add_counts(&mut self.sentinels_nulled, other.sentinels_nulled);
+        add_counts(
+            &mut self.unmapped_complaint_types,
+            other.unmapped_complaint_types,
+        );
+        add_counts(&mut self.corrections, other.corrections);
+        add_counts(&mut self.coordinate_repairs, other.coordinate_repairs);
+        for (rule, outcomes) in other.rule_outcomes {
+            self.rule_outcomes.entry(rule).or_default().add(&outcomes);
+        }
+        add_counts(&mut self.quality_flags, other.quality_flags);
+        self.pristine_records += other.pristine_records;
+        add_counts(&mut self.redactions, other.redactions);
+    }
+}
+
+fn add_counts<K: Ord>(into: &mut BTreeMap<K, u64>, from: BTreeMap<K, u64>) {
+    for (key, count) in from {
+        *into.entry(key).or_insert(0) += count;
+    }
+}
+
+/// A chunk after the transform, with its place in the input.
+#[derive(Debug)]
+pub struct TransformedChunk {
+    /// Position of the chunk in the input, starting at 1
+    pub number: usize,
+    /// Records in the chunk before the transform
+    pub extracted: usize,
+    pub records: Vec<ServiceRequest>,
+}
+
+/// Ordered chain of [`Stage`]s applied to every chunk of a run.
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>,
+    /// Forked stages for each worker after the first; `None` where a stage
+    /// cannot be forked and runs on the calling thread instead
+    replicas: Vec<Vec<Option<Box<dyn Stage>>>>,
+    summary: TransformSummary,
+}
+
+impl Pipeline {
+    /// Starts from the built-in stages; see [`PipelineBuilder`].
+    pub fn builder() -> PipelineBuilder {
+        PipelineBuilder::new()
+    }
+
+    /// Runs exactly `stages`, without any of the built-in ones.
+    pub fn new(stages: Vec<Box<dyn Stage>>) -> Self {
         Self {
-            validator: Validator::new(),
+            stages,
+            replicas: Vec::new(),
+            summary: TransformSummary::default(),
         }
     }
 
-    pub fn transform(&self, mut records: Vec<ServiceRequest>) -> Result<Vec<ServiceRequest>> {
+    /// Lets [`transform_batch`](Self::transform_batch) run forkable stages
+    /// on up to `workers` threads.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.replicas = (1..workers.max(1))
+            .map(|_| self.stages.iter().map(|stage| stage.fork()).collect())
+            .collect();
+        self
+    }
+
+    pub fn workers(&self) -> usize {
+        self.replicas.len() + 1
+    }
+
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    pub fn summary(&self) -> &TransformSummary {
+        &self.summary
+    }
+
+    pub fn transform(&mut self, mut records: Vec<ServiceRequest>) -> Result<Vec<ServiceRequest>> {
         info!("Transforming {} records", records.len());
 
         let initial_count = records.len();
 
-        // Deduplicate by unique_key
-        let mut seen_keys = HashSet::new();
-        records.retain(|record| seen_keys.insert(record.unique_key));
-
-        let after_dedup = records.len();
-        if after_dedup < initial_count {
-            debug!("Removed {} duplicate records", initial_count - after_dedup);
+        for stage in self.stages.iter_mut() {
+            records = run_stage(stage.as_mut(), records, &mut self.summary)?;
         }
 
-        // Clean and validate
-        records.retain(|record| {
-            // Validate unique_key
-            if !self.validator.is_valid_unique_key(record.unique_key) {
-                return false;
-            }
+        info!(
+            "Transformation complete: {} records ({} removed)",
+            records.len(),
+            initial_count.saturating_sub(records.len())
+        );
 
-            // Validate borough if present
-            if let Some(ref borough) = record.borough {
-                if !self.validator.validate_borough(borough) {
-                    return false;
+        Ok(records)
+    }
+
+    /// Transforms several chunks at once. Runs of forkable stages are spread
+    /// over the worker threads, one chunk per worker at a time; every other
+    /// stage runs here and sees the chunks in order. Output and summary are
+    /// the same as calling [`transform`](Self::transform) on each chunk in turn.
+    pub fn transform_batch(
+        &mut self,
+        mut chunks: Vec<Vec<ServiceRequest>>,
+    ) -> Result<Vec<Vec<ServiceRequest>>> {
+        let initial_count: usize = chunks.iter().map(Vec::len).sum();
+        info!(
+            "Transforming {} records in {} chunks on {} workers",
+            initial_count,
+            chunks.len(),
+            self.workers()
+        );
+
+        let mut start = 0;
+        while start < self.stages.len() {
+            let end = self.forkable_run_end(start);
+            if end > start && chunks.len() > 1 {
+                chunks = self.transform_parallel(start..end, chunks)?;
+                start = end;
+            } else {
+                let stage = self.stages[start].as_mut();
+                for chunk in chunks.iter_mut() {
+                    *chunk = run_stage(stage, std::mem::take(chunk), &mut self.summary)?;
                 }
+                start += 1;
             }
+        }
 
-            // Validate coordinates if present
-            if let (Some(lat), Some(lon)) = (record.latitude, record.longitude) {
-                if !self.validator.validate_coordinates(lat, lon) {
-                    return false;
+        let output_count: usize = chunks.iter().map(Vec::len).sum();
+        info!(
+            "Transformation complete: {} records ({} removed)",
+            output_count,
+            initial_count.saturating_sub(output_count)
+        );
+
+        Ok(chunks)
+    }
+
+    /// Transforms the chunks arriving on `input`, one batch of up to one
+    /// chunk per worker at a time, and sends them to `output` in input
+    /// order. Returns the time spent transforming once `input` is closed,
+    /// or early if `output` is. Blocks the calling thread; run it with
+    /// `spawn_blocking`.
+    pub fn transform_stream(
+        &mut self,
+        mut input: mpsc::Receiver<Vec<ServiceRequest>>,
+        output: mpsc::Sender<TransformedChunk>,
+    ) -> Result<Duration> {
+        let mut busy = Duration::ZERO;
+        let mut number = 0;
+        loop {
+            let mut batch = Vec::with_capacity(self.workers());
+            while batch.len() < self.workers() {
+                match input.blocking_recv() {
+                    Some(chunk) => batch.push(chunk),
+                    None => break,
                 }
             }
+            if batch.is_empty() {
+                return Ok(busy);
+            }
 
-            // Validate closed_at is after created_at
-            if let Some(closed) = record.closed_at {
-                if closed < record.created_at {
-                    return false;
+            let extracted: Vec<usize> = batch.iter().map(Vec::len).collect();
+            let started = Instant::now();
+            let transformed = self.transform_batch(batch)?;
+            busy += started.elapsed();
+
+            for (records, extracted) in transformed.into_iter().zip(extracted) {
+                number += 1;
+                let chunk = TransformedChunk {
+                    number,
+                    extracted,
+                    records,
+                };
+                if output.blocking_send(chunk).is_err() {
+                    debug!("Transformed chunk receiver closed, stopping transform");
+                    return Ok(busy);
                 }
             }
+        }
+    }
+
+    /// End of the run of consecutive forkable stages beginning at `start`.
+    fn forkable_run_end(&self, start: usize) -> usize {
+        if self.replicas.is_empty() {
+            return start;
+        }
+        (start..self.stages.len())
+            .find(|&index| self.replicas.iter().any(|stages| stages[index].is_none()))
+            .unwrap_or(self.stages.len())
+    }
 
-            true
+    fn transform_parallel(
+        &mut self,
+        range: Range<usize>,
+        chunks: Vec<Vec<ServiceRequest>>,
+    ) -> Result<Vec<Vec<ServiceRequest>>> {
+        let workers = self.workers();
+        let mut lanes: Vec<Vec<&mut Box<dyn Stage>>> =
+            vec![self.stages[range.clone()].iter_mut().collect()];
+        for stages in self.replicas.iter_mut() {
+            lanes.push(stages[range.clone()].iter_mut().flatten().collect());
+        }
+
+        let mut jobs: Vec<Vec<(usize, Vec<ServiceRequest>)>> =
+            (0..workers).map(|_| Vec::new()).collect();
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            jobs[index % workers].push((index, chunk));
+        }
+
+        let mut results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = lanes
+                .into_iter()
+                .zip(jobs)
+                .map(|(mut stages, jobs)| {
+                    scope.spawn(move || {
+                        jobs.into_iter()
+                            .map(|(index, records)| {
+                                let mut summary = TransformSummary::default();
+                                let result = run_stages(&mut stages, records, &mut summary);
+                                (index, result.map(|records| (records, summary)))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
         });
 
-        let after_validation = records.len();
-        if after_validation < after_dedup {
-            debug!(
-                "Removed {} invalid records",
-                after_dedup - after_validation
-            );
+        // Merge in chunk order so the first failing chunk is the one reported
+        results.sort_by_key(|(index, _)| *index);
+        let mut output = Vec::with_capacity(results.len());
+        for (_, result) in results {
+            let (records, summary) = result?;
+            self.summary.merge(summary);
+            output.push(records);
         }
 
-        info!(
-            "Transformation complete: {} records ({} removed)",
-            after_validation,
-            initial_count - after_validation
-        );
+        Ok(output)
+    }
+}
 
-        Ok(records)
+fn run_stage(
+    stage: &mut dyn Stage,
+    records: Vec<ServiceRequest>,
+    summary: &mut TransformSummary,
+) -> Result<Vec<ServiceRequest>> {
+    let before = records.len();
+    let records = stage
+        .process(records, summary)
+        .with_context(|| format!("Transform stage {} failed", stage.name()))?;
+
+    let removed = before.saturating_sub(records.len());
+    if removed > 0 {
+        debug!(stage = stage.name(), removed, "Stage removed records");
+        *summary
+            .removed_by_stage
+            .entry(stage.name().to_string())
+            .or_insert(0) += removed as u64;
+    }
+
+    Ok(records)
+}
+
+fn run_stages(
+    stages: &mut [&mut Box<dyn Stage>],
+    mut records: Vec<ServiceRequest>,
+    summary: &mut TransformSummary,
+) -> Result<Vec<ServiceRequest>> {
+    for stage in stages.iter_mut() {
+        records = run_stage(stage.as_mut(), records, summary)?;
+    }
+    Ok(records)
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new(PipelineBuilder::new().default_stages())
+    }
+}
+
+/// The fixed transform chain from before [`Pipeline`]; runs the built-in
+/// stages and nothing else.
+#[deprecated(note = "use `Pipeline`, which also takes custom stages")]
+pub struct Transformer {
+    /// Configured by the `with_*` calls, built on the first chunk
+    builder: Option<PipelineBuilder>,
+    pipeline: Pipeline,
+}
+
+#[allow(deprecated)]
+impl Transformer {
+    pub fn new() -> Self {
+        Self {
+            builder: Some(PipelineBuilder::new()),
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    fn configure(mut self, f: impl FnOnce(PipelineBuilder) -> PipelineBuilder) -> Self {
+        self.builder = self.builder.map(f);
+        self
+    }
+
+    pub fn with_policies(self, policies: RulePolicies) -> Self {
+        self.configure(|builder| builder.with_policies(policies))
+    }
+
+    pub fn with_taxonomy(self, taxonomy: Taxonomy) -> Self {
+        self.configure(|builder| builder.with_taxonomy(taxonomy))
+    }
+
+    pub fn with_correctors(self, correctors: FieldCorrectors) -> Self {
+        self.configure(|builder| builder.with_correctors(correctors))
+    }
+
+    pub fn with_grid(self, grid: GridConfig) -> Self {
+        self.configure(|builder| builder.with_grid(grid))
+    }
+
+    pub fn with_redactor(self, redactor: Redactor) -> Self {
+        self.configure(|builder| builder.with_redactor(redactor))
+    }
+
+    pub fn summary(&self) -> &TransformSummary {
+        self.pipeline.summary()
+    }
+
+    pub fn transform(&mut self, records: Vec<ServiceRequest>) -> Result<Vec<ServiceRequest>> {
+        if let Some(builder) = self.builder.take() {
+            self.pipeline = builder.build()?;
+        }
+        self.pipeline.transform(records)
     }
 }
 
+#[allow(deprecated)]
 impl Default for Transformer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+enum Placement {
+    Before(String),
+    After(String),
+    /// Just before quality assessment, so flags the stage sets are counted
+    Default,
+}
+
+/// Configures the built-in stages and splices custom ones around them.
+///
+/// The built-in chain is `dedup`, `normalize`, `sentinels`, `redact`,
+/// `truncate`, `correct`, `repair_coordinates`, `validate`, `classify`, `derive`,
+/// `assess_quality`;
+/// `correct` and
+/// `classify` are only present once correctors or a taxonomy are configured.
+pub struct PipelineBuilder {
+    policies: RulePolicies,
+    validator: Validator,
+    outliers: ResolutionOutliers,
+    taxonomy: Option<Taxonomy>,
+    correctors: Option<FieldCorrectors>,
+    grid: GridConfig,
+    redactor: Redactor,
+    sentinels: Sentinels,
+    normalizer: TextNormalizer,
+    workers: usize,
+    excluded: Vec<String>,
+    custom: Vec<(Placement, Box<dyn Stage>)>,
+}
+
+impl PipelineBuilder {
+    pub fn new() -> Self {
+        Self {
+            policies: RulePolicies::default(),
+            validator: Validator::new(),
+            outliers: ResolutionOutliers::default(),
+            taxonomy: None,
+            correctors: None,
+            grid: GridConfig::default(),
+            redactor: Redactor::default(),
+            sentinels: Sentinels::default(),
+            normalizer: TextNormalizer::default(),
+            workers: 1,
+            excluded: Vec::new(),
+            custom: Vec::new(),
+        }
+    }
+
+    pub fn with_policies(mut self, policies: RulePolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Validator used by the `validate` stage, e.g. with custom temporal bounds.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub fn with_outliers(mut self, outliers: ResolutionOutliers) -> Self {
+        self.outliers = outliers;
+        self
+    }
+
+    pub fn with_taxonomy(mut self, taxonomy: Taxonomy) -> Self {
+        self.taxonomy = Some(taxonomy);
+        self
+    }
+
+    pub fn with_correctors(mut self, correctors: FieldCorrectors) -> Self {
+        self.correctors = Some(correctors);
+        self
+    }
+
+    pub fn with_grid(mut self, grid: GridConfig) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub fn with_sentinels(mut self, sentinels: Sentinels) -> Self {
+        self.sentinels = sentinels;
+        self
+    }
+
+    pub fn with_normalizer(mut self, normalizer: TextNormalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// See [`Pipeline::with_workers`].
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Adds a stage after the built-in ones, before quality assessment.
+    pub fn with_stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.custom.push((Placement::Default, Box::new(stage)));
+        self
+    }
+
+    pub fn with_stage_before(mut self, anchor: &str, stage: impl Stage + 'static) -> Self {
+        self.custom
+            .push((Placement::Before(anchor.to_string()), Box::new(stage)));
+        self
+    }
+
+    pub fn with_stage_after(mut self, anchor: &str, stage: impl Stage + 'static) -> Self {
+        self.custom
+            .push((Placement::After(anchor.to_string()), Box::new(stage)));
+        self
+    }
+
+    /// Drops a built-in stage from the chain.
+    pub fn without_stage(mut self, name: &str) -> Self {
+        self.excluded.push(name.to_string());
+        self
+    }
+
+    /// Assembles the chain; fails if a stage name given to `without_stage` or
+    /// used as an anchor is not part of it.
+    pub fn build(mut self) -> Result<Pipeline> {
+        let custom = std::mem::take(&mut self.custom);
+        let excluded = std::mem::take(&mut self.excluded);
+        let workers = self.workers;
+        let mut stages = self.default_stages();
+
+        for name in &excluded {
+            let index = position(&stages, name)?;
+            stages.remove(index);
+        }
+
+        for (placement, stage) in custom {
+            let index = match placement {
+                Placement::Before(ref anchor) => position(&stages, anchor)?,
+                Placement::After(ref anchor) => position(&stages, anchor)? + 1,
+                Placement::Default => position(&stages, QUALITY_STAGE).unwrap_or(stages.len()),
+            };
+            stages.insert(index, stage);
+        }
+
+        Ok(Pipeline::new(stages).with_workers(workers))
+    }
+
+    fn default_stages(self) -> Vec<Box<dyn Stage>> {
+        let mut stages: Vec<Box<dyn Stage>> = vec![
+            Box::new(DedupStage::default()),
+            Box::new(NormalizeStage::new(self.normalizer.clone())),
+            Box::new(SentinelStage::new(self.sentinels)),
+            Box::new(RedactStage::new(self.redactor)),
+            Box::new(TruncateStage::new(self.normalizer)),
+        ];
+        if let Some(correctors) = self.correctors {
+            stages.push(Box::new(CorrectStage::new(correctors)));
+        }
+        stages.push(Box::new(CoordinateRepairStage::default()));
+        stages.push(Box::new(
+            ValidateStage::new(self.policies)
+                .with_validator(self.validator)
+                .with_outliers(self.outliers),
+        ));
+        if let Some(taxonomy) = self.taxonomy {
+            stages.push(Box::new(ClassifyStage::new(taxonomy)));
+        }
+        stages.push(Box::new(DeriveStage::new(self.grid)));
+        stages.push(Box::new(QualityStage));
+        stages
+    }
+}
+
+impl Default for PipelineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const QUALITY_STAGE: &str = "assess_quality";
+
+fn position(stages: &[Box<dyn Stage>], name: &str) -> Result<usize> {
+    stages
+        .iter()
+        .position(|stage| stage.name() == name)
+        .ok_or_else(|| anyhow!("Unknown transform stage: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clean::QualityFlags;
+    use chrono::{TimeZone, Utc};
+
+    /// Drops records from agencies outside the allow list.
+    struct AgencyFilter(&'static [&'static str]);
+
+    impl Stage for AgencyFilter {
+        fn name(&self) -> &str {
+            "agency_filter"
+        }
+
+        fn process(
+            &mut self,
+            mut records: Vec<ServiceRequest>,
+            _summary: &mut TransformSummary,
+        ) -> Result<Vec<ServiceRequest>> {
+            records.retain(|r| r.agency.as_deref().is_some_and(|a| self.0.contains(&a)));
+            Ok(records)
+        }
+    }
+
+    fn record(unique_key: i64, agency: &str) -> ServiceRequest {
+        ServiceRequest {
+            unique_key,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap(),
+            agency: Some(agency.to_string()),
+            complaint_type: "Noise".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_pipeline_stages() {
+        assert_eq!(
+            Pipeline::default().stage_names(),
+            vec![
+                "dedup",
+                "normalize",
+                "sentinels",
+                "redact",
+                "truncate",
+                "repair_coordinates",
+                "validate",
+                "derive",
+                "assess_quality"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_stage_placement() {
+        let pipeline = Pipeline::builder()
+            .with_stage_after("dedup", AgencyFilter(&["NYPD"]))
+            .without_stage("redact")
+            .build()
+            .unwrap();
+        assert_eq!(
+            &pipeline.stage_names()[..4],
+            &["dedup", "agency_filter", "normalize", "sentinels"]
+        );
+
+        let pipeline = Pipeline::builder()
+            .with_stage(AgencyFilter(&["NYPD"]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            &pipeline.stage_names()[7..],
+            &["derive", "agency_filter", "assess_quality"]
+        );
+
+        assert!(Pipeline::builder()
+            .without_stage("geocode")
+            .build()
+            .is_err());
+        assert!(Pipeline::builder()
+            .with_stage_before("geocode", AgencyFilter(&[]))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_custom_stage_runs_and_is_counted() {
+        let mut pipeline = Pipeline::builder()
+            .with_stage(AgencyFilter(&["NYPD"]))
+            .build()
+            .unwrap();
+
+        let records = vec![record(1, "NYPD"), record(1, "NYPD"), record(2, "DSNY")];
+        let output = pipeline.transform(records).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert!(output[0]
+            .quality_flags
+            .contains(QualityFlags::MISSING_CLOSED_DATE));
+        let removed = &pipeline.summary().removed_by_stage;
+        assert_eq!(removed.get("dedup"), Some(&1));
+        assert_eq!(removed.get("agency_filter"), Some(&1));
+    }
+
+    #[test]
+    fn test_pii_straddling_length_limit_is_redacted() {
+        let mut pipeline = Pipeline::builder()
+            .with_redactor(Redactor::parse("phone").unwrap())
+            .with_normalizer(
+                TextNormalizer::default()
+                    .parse_max_length("descriptor=12")
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let mut input = record(1, "NYPD");
+        input.descriptor = Some("Call 212-555-0147 after 6pm".to_string());
+
+        let output = pipeline.transform(vec![input]).unwrap();
+
+        assert_eq!(output[0].descriptor.as_deref(), Some("Call [PHONE]"));
+        assert!(output[0]
+            .quality_flags
+            .contains(QualityFlags::DESCRIPTOR_TRUNCATED));
+        let summary = pipeline.summary();
+        assert_eq!(summary.redactions.get("phone"), Some(&1));
+        assert_eq!(summary.text_truncated.get("descriptor"), Some(&1));
+    }
+
+    #[test]
+    fn test_outlier_statistics_skip_records_failing_other_rules() {
+        let resolved = |unique_key, hours, borough: &str| {
+            let mut record = record(unique_key, "NYPD");
+            record.created_at = Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap();
+            record.closed_at = Some(record.created_at + chrono::Duration::hours(hours));
+            record.borough = Some(borough.to_string());
+            record
+        };
+        let mut records: Vec<_> = (0..30)
+            .map(|i| resolved(i + 1, 2 + i % 5, "BROOKLYN"))
+            .collect();
+        // Rejected for their borough; were they observed, the spread they add
+        // would hide the real outlier below
+        records.extend((0..10).map(|i| resolved(i + 100, 8000, "ATLANTIS")));
+        records.push(resolved(200, 8000, "BROOKLYN"));
+
+        let mut pipeline = Pipeline::builder()
+            .with_outliers(ResolutionOutliers::new(3.5, 30))
+            .build()
+            .unwrap();
+        let output = pipeline.transform(records).unwrap();
+
+        assert_eq!(output.len(), 31);
+        let outlier = output.iter().find(|r| r.unique_key == 200).unwrap();
+        assert_eq!(outlier.failed_rules, vec!["resolution_outlier"]);
+        let outcomes = &pipeline.summary().rule_outcomes;
+        assert_eq!(outcomes[&Rule::Borough].rejected, 10);
+        assert_eq!(outcomes[&Rule::ResolutionOutlier].flagged, 1);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_transformer_runs_default_pipeline() {
+        let records = || vec![record(1, "nypd"), record(1, "NYPD"), record(-2, "DSNY")];
+        let mut transformer =
+            Transformer::new().with_policies(RulePolicies::parse("borough=flag").unwrap());
+        let mut pipeline = Pipeline::default();
+
+        let output = transformer.transform(records()).unwrap();
+        let expected = pipeline.transform(records()).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].agency.as_deref(), Some("NYPD"));
+        assert_eq!(output[0].row_hash(), expected[0].row_hash());
+        assert_eq!(
+            transformer.summary().removed_by_stage,
+            pipeline.summary().removed_by_stage
+        );
+    }
+
+    #[test]
+    fn test_batch_matches_sequential() {
+        let chunks = || {
+            vec![
+                vec![record(1, "NYPD"), record(2, "DSNY")],
+                vec![record(3, "NYPD"), record(-4, "NYPD")],
+                vec![record(2, "DOT"), record(5, "dsny")],
+                vec![record(6, "HPD")],
+            ]
+        };
+        let hashes = |chunks: &[Vec<ServiceRequest>]| -> Vec<Vec<String>> {
+            chunks
+                .iter()
+                .map(|chunk| chunk.iter().map(|r| r.row_hash()).collect())
+                .collect()
+        };
+
+        let mut sequential = Pipeline::default();
+        let expected: Vec<_> = chunks()
+            .into_iter()
+            .map(|chunk| sequential.transform(chunk).unwrap())
+            .collect();
+
+        let mut parallel = Pipeline::builder().with_workers(3).build().unwrap();
+        assert_eq!(parallel.workers(), 3);
+        let output = parallel.transform_batch(chunks()).unwrap();
+
+        assert_eq!(hashes(&output), hashes(&expected));
+        // Key 2 was already loaded by the first chunk
+        assert!(output[2].iter().all(|r| r.unique_key != 2));
+        assert_eq!(output[2][0].agency.as_deref(), Some("DSNY"));
+
+        let (a, b) = (sequential.summary(), parallel.summary());
+        assert_eq!(a.removed_by_stage, b.removed_by_stage);
+        assert_eq!(a.removed_by_stage.get("dedup"), Some(&1));
+        assert_eq!(a.quality_flags, b.quality_flags);
+        assert_eq!(a.pristine_records, b.pristine_records);
+        assert_eq!(a.text_normalized, b.text_normalized);
+    }
+
+    #[tokio::test]
+    async fn test_transform_stream_keeps_input_order() {
+        let (chunk_tx, chunk_rx) = mpsc::channel(1);
+        let (clean_tx, mut clean_rx) = mpsc::channel(1);
+
+        let mut pipeline = Pipeline::builder().with_workers(2).build().unwrap();
+        let transform =
+            tokio::task::spawn_blocking(move || pipeline.transform_stream(chunk_rx, clean_tx));
+
+        let chunks = vec![
+            vec![record(1, "NYPD"), record(2, "DSNY")],
+            vec![record(3, "NYPD"), record(-4, "NYPD")],
+            vec![record(2, "DOT")],
+        ];
+        for chunk in chunks {
+            chunk_tx.send(chunk).await.unwrap();
+        }
+        drop(chunk_tx);
+
+        let mut received = Vec::new();
+        while let Some(chunk) = clean_rx.recv().await {
+            received.push((chunk.number, chunk.extracted, chunk.records.len()));
+        }
+        assert_eq!(received, vec![(1, 2, 2), (2, 2, 1), (3, 1, 0)]);
+        transform.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+// Geohash encoding
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+pub const MAX_GEOHASH_PRECISION: usize = 12;
+
+/// Encodes a coordinate as a geohash of `precision` characters.
+///
+/// Coarser cells are prefixes of finer ones, so a single stored geohash can
+/// be aggregated at any lower precision with `LEFT(geohash, n)`.
+pub fn encode_geohash(lat: f64, lon: f64, precision: usize) -> String {
+    let precision = precision.clamp(1, MAX_GEOHASH_PRECISION);
+    let mut lat_range = (-90.0, 90.0);
+    let mut lon_range = (-180.0, 180.0);
+    let mut hash = String::with_capacity(precision);
+    let mut even_bit = true;
+    let mut bits = 0u8;
+    let mut bit_count = 0;
+
+    while hash.len() < precision {
+        // Bits alternate between longitude and latitude, starting with longitude
+        let (range, value) = if even_bit {
+            (&mut lon_range, lon)
+        } else {
+            (&mut lat_range, lat)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        bits <<= 1;
+        if value >= mid {
+            bits |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even_bit = !even_bit;
+        bit_count += 1;
+
+        if bit_count == 5 {
+            hash.push(BASE32[bits as usize] as char);
+            bits = 0;
+            bit_count = 0;
+        }
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_geohash_known_values() {
+        assert_eq!(encode_geohash(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(encode_geohash(40.7580, -73.9855, 7), "dr5ru7v");
+    }
+
+    #[test]
+    fn test_encode_geohash_coarser_precision_is_prefix() {
+        let fine = encode_geohash(40.6782, -73.9442, 9);
+        let coarse = encode_geohash(40.6782, -73.9442, 5);
+        assert!(fine.starts_with(&coarse));
+    }
+}
@@ -0,0 +1,121 @@
+// Hexagonal grid cell assignment
+//
+// Coordinates are projected onto a local equirectangular plane centred on NYC,
+// which keeps distortion well under 1% across the five boroughs, and binned
+// into pointy-top hexagons addressed by axial (q, r) coordinates.
+
+pub const DEFAULT_HEX_CELL_METERS: f64 = 250.0;
+
+const ORIGIN_LAT: f64 = 40.7128;
+const ORIGIN_LON: f64 = -74.0060;
+const METERS_PER_DEGREE_LAT: f64 = 110_574.0;
+const METERS_PER_DEGREE_LON_AT_EQUATOR: f64 = 111_320.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HexCell {
+    pub q: i64,
+    pub r: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HexGrid {
+    /// Distance from a hexagon's centre to any of its corners
+    cell_meters: f64,
+}
+
+impl HexGrid {
+    pub fn new(cell_meters: f64) -> Self {
+        Self { cell_meters }
+    }
+
+    pub fn cell_meters(&self) -> f64 {
+        self.cell_meters
+    }
+
+    pub fn cell(&self, lat: f64, lon: f64) -> HexCell {
+        let (x, y) = project(lat, lon);
+        let q = (3f64.sqrt() / 3.0 * x - y / 3.0) / self.cell_meters;
+        let r = (2.0 / 3.0 * y) / self.cell_meters;
+        round_axial(q, r)
+    }
+
+    /// Latitude/longitude of a cell's centre.
+    pub fn center(&self, cell: HexCell) -> (f64, f64) {
+        let (q, r) = (cell.q as f64, cell.r as f64);
+        let x = self.cell_meters * 3f64.sqrt() * (q + r / 2.0);
+        let y = self.cell_meters * 1.5 * r;
+        unproject(x, y)
+    }
+
+    /// Stable text identifier for a cell; includes the cell size so grids of
+    /// different resolutions never share identifiers.
+    pub fn cell_id(&self, lat: f64, lon: f64) -> String {
+        let cell = self.cell(lat, lon);
+        format!("{}:{}:{}", self.cell_meters, cell.q, cell.r)
+    }
+}
+
+impl Default for HexGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEX_CELL_METERS)
+    }
+}
+
+fn meters_per_degree_lon() -> f64 {
+    METERS_PER_DEGREE_LON_AT_EQUATOR * ORIGIN_LAT.to_radians().cos()
+}
+
+fn project(lat: f64, lon: f64) -> (f64, f64) {
+    let x = (lon - ORIGIN_LON) * meters_per_degree_lon();
+    let y = (lat - ORIGIN_LAT) * METERS_PER_DEGREE_LAT;
+    (x, y)
+}
+
+fn unproject(x: f64, y: f64) -> (f64, f64) {
+    let lat = ORIGIN_LAT + y / METERS_PER_DEGREE_LAT;
+    let lon = ORIGIN_LON + x / meters_per_degree_lon();
+    (lat, lon)
+}
+
+/// Rounds fractional axial coordinates to the containing hexagon via cube
+/// coordinates (x + y + z = 0), fixing the component with the largest error.
+fn round_axial(q: f64, r: f64) -> HexCell {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    HexCell {
+        q: rq as i64,
+        r: rr as i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_cell_origin_and_neighbours() {
+        let grid = HexGrid::new(250.0);
+        assert_eq!(grid.cell(ORIGIN_LAT, ORIGIN_LON), HexCell { q: 0, r: 0 });
+
+        // Points ~100m from a cell's centre stay inside it; ~2km away do not
+        let here = grid.cell(40.7580, -73.9855);
+        let (lat, lon) = grid.center(here);
+        assert_eq!(grid.cell(lat, lon), here);
+        assert_eq!(grid.cell(lat + 0.0009, lon), here);
+        assert_ne!(grid.cell(lat + 0.018, lon), here);
+    }
+
+    #[test]
+    fn test_hex_cell_id_includes_size() {
+        let grid = HexGrid::new(500.0);
+        assert!(grid.cell_id(40.7580, -73.9855).starts_with("500:"));
+    }
+}
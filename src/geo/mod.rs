@@ -0,0 +1,9 @@
+// Geospatial helpers - grid cell encodings and projections
+pub mod geohash;
+pub mod hexgrid;
+pub mod state_plane;
+
+// Re-exports
+pub use geohash::*;
+pub use hexgrid::*;
+pub use state_plane::*;
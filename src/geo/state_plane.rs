@@ -0,0 +1,129 @@
+// NAD83 / New York Long Island State Plane (EPSG:2263) conversion
+//
+// EPSG:2263 is a Lambert Conformal Conic (2SP) projection on the GRS80
+// ellipsoid with coordinates in US survey feet. NAD83 and WGS84 differ by
+// around a metre, well below the precision of 311 geocoding, so the inverse
+// projection result is used as WGS84 directly.
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+const SEMI_MAJOR_AXIS_M: f64 = 6_378_137.0;
+const INVERSE_FLATTENING: f64 = 298.257_222_101;
+const US_SURVEY_FOOT_M: f64 = 1200.0 / 3937.0;
+
+const STANDARD_PARALLEL_1: f64 = 41.0 + 2.0 / 60.0;
+const STANDARD_PARALLEL_2: f64 = 40.0 + 40.0 / 60.0;
+const ORIGIN_LAT: f64 = 40.0 + 10.0 / 60.0;
+const CENTRAL_MERIDIAN: f64 = -74.0;
+const FALSE_EASTING_M: f64 = 300_000.0;
+const FALSE_NORTHING_M: f64 = 0.0;
+
+const LATITUDE_TOLERANCE: f64 = 1e-12;
+const MAX_ITERATIONS: usize = 15;
+
+struct Projection {
+    e: f64,
+    n: f64,
+    a_f: f64,
+    rho0: f64,
+}
+
+impl Projection {
+    fn new() -> Self {
+        let f = 1.0 / INVERSE_FLATTENING;
+        let e = (2.0 * f - f * f).sqrt();
+
+        let phi1 = STANDARD_PARALLEL_1.to_radians();
+        let phi2 = STANDARD_PARALLEL_2.to_radians();
+        let phi0 = ORIGIN_LAT.to_radians();
+
+        let (m1, m2) = (m(phi1, e), m(phi2, e));
+        let (t1, t2, t0) = (t(phi1, e), t(phi2, e), t(phi0, e));
+
+        let n = (m1.ln() - m2.ln()) / (t1.ln() - t2.ln());
+        let a_f = SEMI_MAJOR_AXIS_M * m1 / (n * t1.powf(n));
+        let rho0 = a_f * t0.powf(n);
+
+        Self { e, n, a_f, rho0 }
+    }
+}
+
+fn m(phi: f64, e: f64) -> f64 {
+    phi.cos() / (1.0 - (e * phi.sin()).powi(2)).sqrt()
+}
+
+fn t(phi: f64, e: f64) -> f64 {
+    let e_sin = e * phi.sin();
+    (FRAC_PI_4 - phi / 2.0).tan() / ((1.0 - e_sin) / (1.0 + e_sin)).powf(e / 2.0)
+}
+
+/// Converts EPSG:2263 easting/northing in US survey feet to `(lat, lon)`.
+pub fn state_plane_to_wgs84(x_feet: f64, y_feet: f64) -> (f64, f64) {
+    let p = Projection::new();
+
+    let x = x_feet * US_SURVEY_FOOT_M - FALSE_EASTING_M;
+    let y = p.rho0 - (y_feet * US_SURVEY_FOOT_M - FALSE_NORTHING_M);
+
+    let rho = (x * x + y * y).sqrt();
+    let theta = x.atan2(y);
+    let t_value = (rho / p.a_f).powf(1.0 / p.n);
+
+    // Latitude has no closed form on the ellipsoid; iterate from the
+    // spherical approximation until it converges
+    let mut phi = FRAC_PI_2 - 2.0 * t_value.atan();
+    for _ in 0..MAX_ITERATIONS {
+        let e_sin = p.e * phi.sin();
+        let next =
+            FRAC_PI_2 - 2.0 * (t_value * ((1.0 - e_sin) / (1.0 + e_sin)).powf(p.e / 2.0)).atan();
+        let converged = (next - phi).abs() < LATITUDE_TOLERANCE;
+        phi = next;
+        if converged {
+            break;
+        }
+    }
+
+    let lambda = theta / p.n + CENTRAL_MERIDIAN.to_radians();
+    (phi.to_degrees(), lambda.to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wgs84_to_state_plane(lat: f64, lon: f64) -> (f64, f64) {
+        let p = Projection::new();
+        let rho = p.a_f * t(lat.to_radians(), p.e).powf(p.n);
+        let theta = p.n * (lon - CENTRAL_MERIDIAN).to_radians();
+        let x = FALSE_EASTING_M + rho * theta.sin();
+        let y = FALSE_NORTHING_M + p.rho0 - rho * theta.cos();
+        (x / US_SURVEY_FOOT_M, y / US_SURVEY_FOOT_M)
+    }
+
+    #[test]
+    fn test_state_plane_origin_maps_to_projection_origin() {
+        let (lat, lon) = state_plane_to_wgs84(FALSE_EASTING_M / US_SURVEY_FOOT_M, 0.0);
+        assert!((lat - ORIGIN_LAT).abs() < 1e-9);
+        assert!((lon - CENTRAL_MERIDIAN).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_state_plane_round_trip() {
+        for &(lat, lon) in &[
+            (40.7580, -73.9855),
+            (40.5795, -74.1502),
+            (40.8448, -73.8648),
+        ] {
+            let (x, y) = wgs84_to_state_plane(lat, lon);
+            let (back_lat, back_lon) = state_plane_to_wgs84(x, y);
+            assert!((back_lat - lat).abs() < 1e-9);
+            assert!((back_lon - lon).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_state_plane_times_square() {
+        // Times Square sits at roughly X 988,300 / Y 215,500 ft
+        let (lat, lon) = state_plane_to_wgs84(988_300.0, 215_500.0);
+        assert!((lat - 40.7580).abs() < 0.002);
+        assert!((lon - (-73.9855)).abs() < 0.002);
+    }
+}
@@ -0,0 +1,7 @@
+// UrbanFlux - ETL library for NYC 311 Service Request data
+pub mod clean;
+pub mod config;
+pub mod db;
+pub mod etl;
+pub mod geo;
+pub mod logging;
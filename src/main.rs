@@ -1,13 +1,14 @@
-// Module declarations
-pub mod clean;
-pub mod config;
-pub mod db;
-pub mod etl;
-pub mod logging;
-
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::{Parser, Subcommand};
-use tracing::info;
+use futures::future::join_all;
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+
+use urbanflux::{clean, config, db, etl, geo, logging};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -26,7 +27,7 @@ struct Cli {
 enum Commands {
     /// Run ETL pipeline
     Run {
-        /// Mode: full or incremental
+        /// Mode: full (replace all rows) or incremental
         #[arg(short, long, default_value = "full")]
         mode: String,
 
@@ -64,12 +65,28 @@ enum DbCommands {
         #[arg(long, default_value = "false")]
         concurrently: bool,
     },
+    /// Create upcoming monthly partitions and detach old ones
+    MaintainPartitions {
+        /// Months past the current one to create partitions for
+        #[arg(long, default_value_t = db::DEFAULT_PARTITIONS_AHEAD)]
+        ahead: u32,
+
+        /// Detach partitions ending more than this many months before the
+        /// current one; nothing is detached if unset
+        #[arg(long)]
+        retain_months: Option<u32>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 enum ReportCommands {
     /// Show last run summary
     LastRun,
+    /// Show every loaded version of a service request
+    History {
+        /// Service request unique_key
+        unique_key: i64,
+    },
 }
 
 #[tokio::main]
@@ -103,55 +120,321 @@ async fn main() -> Result<()> {
                 println!("🔍 DRY RUN MODE - No database writes will occur\n");
             }
 
-            // Extract
-            println!("📥 Extracting data from CSV...");
-            let extractor = etl::Extractor::new(chunk_size);
-            let chunks = extractor.extract(&input).await?;
-
-            let total_extracted: usize = chunks.iter().map(|c| c.len()).sum();
-            println!("✅ Extracted {} records in {} chunks", total_extracted, chunks.len());
-
             // Transform and Load
-            let mut total_loaded = 0u64;
             let mut total_rejected = 0usize;
 
-            let transformer = etl::Transformer::new();
+            let taxonomy = match config.etl.taxonomy_path {
+                Some(ref path) => {
+                    let taxonomy = clean::Taxonomy::load(path)?;
+                    info!(path = %path, entries = taxonomy.len(), "Loaded complaint taxonomy");
+                    Some(taxonomy)
+                }
+                None => None,
+            };
+
+            let aliases = match config.etl.correction_aliases_path {
+                Some(ref path) => clean::CorrectionAliases::load(path)?,
+                None => clean::CorrectionAliases::default(),
+            };
+            let complaint_types = taxonomy
+                .as_ref()
+                .map(|t| t.complaint_types())
+                .unwrap_or_default();
+            let correctors = clean::FieldCorrectors::new(
+                config.etl.correction_threshold,
+                &aliases,
+                &complaint_types,
+            );
+
+            let grid = etl::GridConfig {
+                geohash_precision: config.etl.geohash_precision,
+                hex_grid: geo::HexGrid::new(config.etl.hex_cell_meters),
+            };
+
+            let bounds = clean::TemporalBounds::new(
+                config.etl.earliest_date,
+                chrono::Duration::hours(config.etl.max_future_skew_hours),
+                chrono::Utc::now(),
+            );
 
             // Only connect to DB if not in dry-run mode
             let db = if !dry_run {
-                Some(db::Database::connect(&config.database_url()).await?)
+                Some(connect(&config).await?)
             } else {
                 None
             };
 
-            for (i, chunk) in chunks.into_iter().enumerate() {
-                println!("🔄 Processing chunk {}/...", i + 1);
-                
-                let initial_count = chunk.len();
-                let clean_records = transformer.transform(chunk)?;
-                let rejected = initial_count - clean_records.len();
-                total_rejected += rejected;
+            // An incremental run skips what earlier runs loaded, less the
+            // lookback window
+            let run_mode: db::RunMode = mode.parse()?;
+            let previous_watermark = match db {
+                Some(ref database) => database.latest_watermark().await?,
+                None => None,
+            };
+            let skip_through = match (run_mode, previous_watermark) {
+                (db::RunMode::Incremental, Some(watermark)) => Some(
+                    watermark
+                        .lookback(chrono::Duration::hours(config.etl.watermark_lookback_hours)),
+                ),
+                _ => None,
+            };
+            if let Some(watermark) = skip_through {
+                println!(
+                    "⏩ Skipping records at or before {} (key {})",
+                    watermark.created_at, watermark.unique_key
+                );
+            }
+
+            let mut builder = etl::Pipeline::builder()
+                .with_validator(clean::Validator::new().with_temporal_bounds(bounds))
+                .with_outliers(clean::ResolutionOutliers::new(
+                    config.etl.outlier_z_threshold,
+                    config.etl.outlier_min_samples,
+                ))
+                .with_policies(clean::RulePolicies::parse(&config.etl.rule_policies)?)
+                .with_correctors(correctors)
+                .with_grid(grid)
+                .with_redactor(clean::Redactor::parse(&config.etl.pii_detectors)?)
+                .with_normalizer(config.text_normalizer()?)
+                .with_sentinels(clean::Sentinels::parse(&config.etl.sentinels)?)
+                .with_workers(config.etl.workers);
+            if let Some(taxonomy) = taxonomy {
+                builder = builder.with_taxonomy(taxonomy);
+            }
+            if let Some(watermark) = skip_through {
+                builder = builder.with_stage_before("dedup", etl::WatermarkStage::new(watermark));
+            }
+            if let Some(ref filter) = config.etl.filter {
+                builder = builder.with_stage(etl::FilterStage::new(
+                    etl::Expression::compile_predicate(filter)?,
+                ));
+            }
+            let derived_columns = etl::DerivedColumn::parse_list(&config.etl.derived_columns)?;
+            if !derived_columns.is_empty() {
+                builder = builder.with_stage(etl::DeriveColumnsStage::new(derived_columns));
+            }
+            let mut pipeline = builder.build()?;
+
+            let mut loader = db.as_ref().map(|database| {
+                let loader = etl::Loader::new(database.clone())
+                    .with_mode(config.etl.load_mode)
+                    .with_temporal_bounds(bounds)
+                    .with_error_budget(config.error_budget());
+                match run_mode {
+                    db::RunMode::Full => loader.with_full_refresh(config.swap_thresholds()),
+                    db::RunMode::Incremental => loader.with_previous_watermark(previous_watermark),
+                }
+            });
+
+            // Every run is recorded in etl_watermarks from here on, and
+            // finishes as completed or failed
+            let run_id = match db {
+                Some(ref database) => Some(database.start_run(run_mode, &input).await?),
+                None => None,
+            };
+
+            // Extract, transform and load overlap: each stage hands its
+            // chunks to the next over a bounded channel, so a slow stage
+            // holds back the ones before it instead of buffering the input
+            let loaders = config.etl.loaders;
+            let mut total_processed = 0usize;
+            let started = std::time::Instant::now();
+            let run = async {
+                let (chunk_tx, chunk_rx) = mpsc::channel(pipeline.workers());
+                let (clean_tx, clean_rx) = mpsc::channel(loaders);
+                let (loaded_tx, mut loaded_rx) = mpsc::channel(loaders);
+
+                let mut workers = Vec::new();
+                if let Some(ref mut loader) = loader {
+                    for _ in 0..loaders {
+                        workers.push(loader.worker().await?);
+                    }
+                }
+
+                // Extract
+                println!("📥 Extracting data from CSV...");
+                let extract = async {
+                    let extractor = etl::Extractor::new(chunk_size);
+                    let stats = extractor.extract_into(&input, chunk_tx).await?;
+                    println!(
+                        "✅ Extracted {} records in {} chunks",
+                        stats.records, stats.chunks
+                    );
+                    Ok::<_, anyhow::Error>(stats)
+                };
+
+                // Transform on a blocking thread so CPU-bound stages never
+                // stall the async runtime
+                let mut owned = std::mem::replace(&mut pipeline, etl::Pipeline::new(Vec::new()));
+                let transform = async {
+                    let (returned, busy) = tokio::task::spawn_blocking(move || {
+                        let busy = owned.transform_stream(chunk_rx, clean_tx);
+                        (owned, busy)
+                    })
+                    .await?;
+                    pipeline = returned;
+                    busy
+                };
+
+                // Load with each worker taking the next chunk when it is free
+                let load = async move {
+                    if workers.is_empty() {
+                        let mut clean_rx = clean_rx;
+                        while let Some(chunk) = clean_rx.recv().await {
+                            if loaded_tx
+                                .send(etl::LoadedChunk::unloaded(&chunk))
+                                .await
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                    } else {
+                        let chunks = Arc::new(Mutex::new(clean_rx));
+                        join_all(
+                            workers
+                                .into_iter()
+                                .map(|worker| worker.run(chunks.clone(), loaded_tx.clone())),
+                        )
+                        .await;
+                    }
+                    Ok(())
+                };
+
+                let collect = async {
+                    let mut load_busy = Duration::ZERO;
+                    while let Some(chunk) = loaded_rx.recv().await {
+                        println!("🔄 Processed chunk {}", chunk.number);
+                        total_processed += chunk.extracted;
+                        total_rejected += chunk.extracted - chunk.kept;
+                        load_busy += chunk.elapsed;
+
+                        if let (Some(ref database), Some(ref mut loader), Some(run_id)) =
+                            (&db, &mut loader, run_id)
+                        {
+                            let Some(load) = chunk.load else { continue };
+                            let stats = load.stats;
+                            println!(
+                                "   inserted {}, updated {}, unchanged {}, quarantined {}, failed {}",
+                                stats.inserted,
+                                stats.updated,
+                                stats.unchanged,
+                                stats.quarantined,
+                                stats.failed
+                            );
+                            loader.record(&load)?;
+                            let counts = run_counts(total_processed, total_rejected, loader);
+                            database.update_run(run_id, &counts).await?;
+                        }
+                    }
+                    Ok(load_busy)
+                };
+
+                let (extracted, transform_busy, (), load_busy) =
+                    tokio::try_join!(extract, transform, load, collect)?;
+
+                let mut watermark = None;
+                if let Some(ref mut loader) = loader {
+                    if let Some(swap) = loader.finish().await? {
+                        println!(
+                            "🔁 Swapped in {} rows (replacing {})",
+                            swap.rows, swap.previous_rows
+                        );
+                    }
+
+                    // A full refresh replaced everything earlier runs loaded,
+                    // so only incremental loaders start from the previous one
+                    watermark = loader.high_water();
+                }
+
+                let timings = StageTimings {
+                    extract: extracted.busy,
+                    transform: transform_busy,
+                    load: load_busy,
+                    wall: started.elapsed(),
+                };
+                Ok((watermark, timings))
+            };
+
+            // A panic, Ctrl-C or SIGTERM ends the run like an error, so it
+            // is still cleaned up and recorded as failed
+            let run_result: Result<(Option<db::Watermark>, StageTimings)> = tokio::select! {
+                result = AssertUnwindSafe(run).catch_unwind() => result.unwrap_or_else(|panic| {
+                    Err(anyhow!("Run panicked: {}", panic_message(panic.as_ref())))
+                }),
+                Ok(()) = tokio::signal::ctrl_c() => Err(anyhow!("Run interrupted")),
+                () = terminated() => Err(anyhow!("Run terminated")),
+            };
 
-                if let Some(ref database) = db {
-                    let loader = etl::Loader::new(database.clone());
-                    let inserted = loader.load(clean_records).await?;
-                    total_loaded += inserted;
+            // Staging tables (and an unswapped shadow table) are dropped
+            // whether or not the run succeeded
+            if let Some(ref mut loader) = loader {
+                if let Err(e) = loader.cleanup().await {
+                    warn!("Failed to clean up staging tables: {:#}", e);
                 }
             }
 
+            if let (Some(ref database), Some(ref loader), Some(run_id)) = (&db, &loader, run_id) {
+                let counts = run_counts(total_processed, total_rejected, loader);
+                match run_result {
+                    Ok((watermark, _)) => database.complete_run(run_id, watermark, &counts).await?,
+                    Err(ref e) => {
+                        let message = format!("{:#}", e);
+                        if let Err(e) = database.fail_run(run_id, &message, &counts).await {
+                            warn!("Failed to record run failure: {:#}", e);
+                        }
+                    }
+                }
+            }
+            let (_, timings) = run_result?;
+
             println!("\n📊 ETL Summary:");
-            println!("  Total extracted: {}", total_extracted);
+            println!("  Total extracted: {}", total_processed);
             println!("  Total rejected:  {}", total_rejected);
-            if let Some(ref database) = db {
-                println!("  Total loaded:    {}", total_loaded);
-                
+            if let (Some(ref database), Some(ref loader)) = (&db, &loader) {
+                let load_totals = loader.totals();
+                println!("  Total inserted:  {}", load_totals.inserted);
+                println!("  Total updated:   {}", load_totals.updated);
+                println!("  Total unchanged: {}", load_totals.unchanged);
+                if load_totals.quarantined > 0 {
+                    println!("  Quarantined:     {}", load_totals.quarantined);
+                }
+                if load_totals.failed > 0 {
+                    println!(
+                        "  Failed:          {} ({} chunks)",
+                        load_totals.failed,
+                        loader.failed_chunks()
+                    );
+                }
+
                 let count = database.get_record_count().await?;
                 println!("  Records in DB:   {}", count);
             } else {
-                println!("  Would load:      {}", total_extracted - total_rejected);
+                println!("  Would load:      {}", total_processed - total_rejected);
             }
+            print_stage_timings(&timings, if dry_run { 0 } else { loaders });
 
-            println!("\n✨ ETL pipeline completed successfully!");
+            print_removed_by_stage(pipeline.summary());
+            print_rule_outcomes(pipeline.summary());
+            print_quality_flags(pipeline.summary());
+            print_corrections(pipeline.summary());
+            print_text_normalization(pipeline.summary());
+            print_sentinels(pipeline.summary());
+            print_coordinate_repairs(pipeline.summary());
+            print_redactions(pipeline.summary());
+            print_unmapped_complaint_types(pipeline.summary());
+
+            match loader
+                .as_ref()
+                .map(|l| l.totals().failed)
+                .filter(|&failed| failed > 0)
+            {
+                Some(failed) => println!(
+                    "\n⚠️  ETL pipeline completed with {} failed records (within the error budget)",
+                    failed
+                ),
+                None => println!("\n✨ ETL pipeline completed successfully!"),
+            }
             Ok(())
         }
         Commands::Db { command } => match command {
@@ -159,8 +442,16 @@ async fn main() -> Result<()> {
                 info!("Initializing database schema");
                 println!("🔧 Initializing database schema...");
 
-                let db = db::Database::connect(&config.database_url()).await?;
-                db.initialize_schema().await?;
+                let db = connect(&config).await?;
+                db.initialize_schema(config.etl.partitioning).await?;
+
+                let partitioned = db.partition_months("service_requests").await?.is_some();
+                if config.etl.partitioning == db::Partitioning::Monthly && !partitioned {
+                    warn!(
+                        "service_requests already exists unpartitioned; \
+                         ETL_PARTITIONING only applies when it is created"
+                    );
+                }
 
                 println!("✅ Database schema initialized successfully!");
                 Ok(())
@@ -169,12 +460,37 @@ async fn main() -> Result<()> {
                 info!(concurrently = concurrently, "Refreshing materialized views");
                 println!("🔄 Refreshing materialized views...");
 
-                let db = db::Database::connect(&config.database_url()).await?;
+                let db = connect(&config).await?;
                 db.refresh_materialized_views(concurrently).await?;
 
                 println!("✅ Materialized views refreshed successfully!");
                 Ok(())
             }
+            DbCommands::MaintainPartitions {
+                ahead,
+                retain_months,
+            } => {
+                info!(ahead = ahead, retain_months = ?retain_months, "Maintaining partitions");
+                println!("🗂️  Maintaining service_requests partitions...");
+
+                let db = connect(&config).await?;
+                let maintenance = db
+                    .maintain_partitions(chrono::Utc::now(), ahead, retain_months)
+                    .await?;
+
+                for partition in &maintenance.created {
+                    println!("  created  {}", partition);
+                }
+                for partition in &maintenance.detached {
+                    println!("  detached {}", partition);
+                }
+                println!(
+                    "✅ Created {} and detached {} partitions",
+                    maintenance.created.len(),
+                    maintenance.detached.len()
+                );
+                Ok(())
+            }
         },
         Commands::Report { command } => match command {
             ReportCommands::LastRun => {
@@ -182,13 +498,269 @@ async fn main() -> Result<()> {
                 println!("📊 Last Run Report");
                 println!("─────────────────────");
 
-                let db = db::Database::connect(&config.database_url()).await?;
+                let db = connect(&config).await?;
                 let count = db.get_record_count().await?;
+                let Some(run) = db.latest_run().await? else {
+                    println!("No runs recorded yet");
+                    println!("Total records in database: {}", count);
+                    return Ok(());
+                };
 
-                println!("Total records in database: {}", count);
-                println!("\nNote: Detailed run reports will be implemented in future phase");
+                println!("Run:       #{} ({})", run.id, run.run_mode);
+                println!("Status:    {}", run.status);
+                println!("Input:     {}", run.input.as_deref().unwrap_or("-"));
+                println!("Started:   {}", run.started_at.to_rfc3339());
+                match (run.completed_at, run.duration()) {
+                    (Some(completed_at), Some(duration)) => println!(
+                        "Completed: {} ({:.1}s)",
+                        completed_at.to_rfc3339(),
+                        duration.num_milliseconds() as f64 / 1000.0
+                    ),
+                    _ => println!("Completed: -"),
+                }
+                println!("Processed: {}", run.rows_processed);
+                println!("Inserted:  {}", run.rows_inserted);
+                println!("Skipped:   {}", run.rows_skipped);
+                if let (Some(created_at), Some(unique_key)) =
+                    (run.last_created_at, run.last_unique_key)
+                {
+                    println!(
+                        "Watermark: {} (unique_key {})",
+                        created_at.to_rfc3339(),
+                        unique_key
+                    );
+                }
+                if let Some(ref error) = run.error_message {
+                    println!("Error:     {}", error);
+                }
+                println!("\nTotal records in database: {}", count);
+                Ok(())
+            }
+            ReportCommands::History { unique_key } => {
+                info!(unique_key = unique_key, "Generating request history report");
+
+                let db = connect(&config).await?;
+                let versions = db.request_history(unique_key).await?;
+                if versions.is_empty() {
+                    println!("No history recorded for request {}", unique_key);
+                    return Ok(());
+                }
+
+                println!("📜 History of request {}", unique_key);
+                println!("─────────────────────");
+                for version in &versions {
+                    let valid_to = version
+                        .valid_to
+                        .map(|t| t.to_rfc3339())
+                        .unwrap_or_else(|| "current".to_string());
+                    println!("{} → {}", version.valid_from.to_rfc3339(), valid_to);
+                    println!("  agency:     {}", version.agency.as_deref().unwrap_or("-"));
+                    println!(
+                        "  status:     {}",
+                        match version.closed_at {
+                            Some(closed_at) => format!("closed {}", closed_at.to_rfc3339()),
+                            None => "open".to_string(),
+                        }
+                    );
+                    if let Some(ref resolution) = version.resolution_description {
+                        println!("  resolution: {}", resolution);
+                    }
+                }
                 Ok(())
             }
         },
     }
 }
+
+/// Time each stage of a run spent working, excluding time spent waiting on
+/// the stages around it.
+struct StageTimings {
+    extract: Duration,
+    transform: Duration,
+    /// Summed over the loaders
+    load: Duration,
+    wall: Duration,
+}
+
+/// Connects with a pool large enough for the configured loaders.
+async fn connect(config: &config::Config) -> Result<db::Database> {
+    db::Database::connect_with(
+        &config.database_url(),
+        config.max_connections(),
+        config.retry_policy(),
+    )
+    .await
+}
+
+fn run_counts(processed: usize, rejected: usize, loader: &etl::Loader) -> db::RunCounts {
+    db::RunCounts {
+        processed: processed as u64,
+        inserted: loader.totals().inserted,
+        skipped: rejected as u64,
+    }
+}
+
+/// Message a caught panic was raised with.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Resolves when the process is asked to stop with SIGTERM, as `docker stop`
+/// and orchestrators do.
+#[cfg(unix)]
+async fn terminated() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+        }
+        Err(e) => {
+            warn!("Failed to listen for SIGTERM: {:#}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminated() {
+    std::future::pending::<()>().await;
+}
+
+fn print_stage_timings(timings: &StageTimings, loaders: usize) {
+    println!("\n⏱️  Stage timings:");
+    println!("  Extract:   {:>8.2}s", timings.extract.as_secs_f64());
+    println!("  Transform: {:>8.2}s", timings.transform.as_secs_f64());
+    if loaders > 0 {
+        println!(
+            "  Load:      {:>8.2}s ({} loaders)",
+            timings.load.as_secs_f64(),
+            loaders
+        );
+    }
+    println!("  Wall time: {:>8.2}s", timings.wall.as_secs_f64());
+}
+
+fn print_removed_by_stage(summary: &etl::TransformSummary) {
+    if summary.removed_by_stage.is_empty() {
+        return;
+    }
+
+    println!("\n🗑️  Removed by stage:");
+    for (stage, count) in &summary.removed_by_stage {
+        println!("  {:>8}  {}", count, stage);
+    }
+}
+
+fn print_rule_outcomes(summary: &etl::TransformSummary) {
+    if summary.rule_outcomes.is_empty() {
+        return;
+    }
+
+    println!("\n🧪 Validation failures:");
+    println!(
+        "  {:<24} {:>8} {:>8} {:>8} {:>11}",
+        "rule", "rejected", "nulled", "flagged", "quarantined"
+    );
+    for (rule, outcomes) in &summary.rule_outcomes {
+        println!(
+            "  {:<24} {:>8} {:>8} {:>8} {:>11}",
+            rule.as_str(),
+            outcomes.rejected,
+            outcomes.nullified,
+            outcomes.flagged,
+            outcomes.quarantined
+        );
+    }
+}
+
+fn print_quality_flags(summary: &etl::TransformSummary) {
+    println!("\n🏷️  Data quality:");
+    println!("  {:>8}  pristine", summary.pristine_records);
+    for (flag, count) in &summary.quality_flags {
+        println!("  {:>8}  {}", count, flag);
+    }
+}
+
+fn print_corrections(summary: &etl::TransformSummary) {
+    if summary.corrections.is_empty() {
+        return;
+    }
+
+    println!("\n✏️  Corrected values:");
+    for (field, count) in &summary.corrections {
+        println!("  {:>8}  {}", count, field);
+    }
+}
+
+fn print_text_normalization(summary: &etl::TransformSummary) {
+    if summary.text_normalized.is_empty() && summary.text_truncated.is_empty() {
+        return;
+    }
+
+    println!("\n🔤 Text normalization:");
+    for (field, count) in &summary.text_normalized {
+        println!("  {:>8}  {} normalized", count, field);
+    }
+    for (field, count) in &summary.text_truncated {
+        println!("  {:>8}  {} truncated", count, field);
+    }
+}
+
+fn print_sentinels(summary: &etl::TransformSummary) {
+    if summary.sentinels_nulled.is_empty() {
+        return;
+    }
+
+    println!("\n🚫 Placeholder values set to NULL:");
+    for (field, count) in &summary.sentinels_nulled {
+        println!("  {:>8}  {}", count, field);
+    }
+}
+
+fn print_coordinate_repairs(summary: &etl::TransformSummary) {
+    if summary.coordinate_repairs.is_empty() {
+        return;
+    }
+
+    println!("\n📍 Coordinate repairs:");
+    for (repair, count) in &summary.coordinate_repairs {
+        println!("  {:>8}  {}", count, repair);
+    }
+}
+
+fn print_redactions(summary: &etl::TransformSummary) {
+    if summary.redactions.is_empty() {
+        return;
+    }
+
+    println!("\n🔒 PII redactions:");
+    for (detector, count) in &summary.redactions {
+        println!("  {:>8}  {}", count, detector);
+    }
+}
+
+const UNMAPPED_REPORT_LIMIT: usize = 20;
+
+fn print_unmapped_complaint_types(summary: &etl::TransformSummary) {
+    let unmapped = &summary.unmapped_complaint_types;
+    if unmapped.is_empty() {
+        return;
+    }
+
+    let mut by_count: Vec<_> = unmapped.iter().collect();
+    by_count.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    println!("\n⚠️  Unmapped complaint types ({}):", unmapped.len());
+    for (complaint_type, count) in by_count.iter().take(UNMAPPED_REPORT_LIMIT) {
+        println!("  {:>8}  {}", count, complaint_type);
+        info!(complaint_type = %complaint_type, count = **count, "Unmapped complaint type");
+    }
+    if by_count.len() > UNMAPPED_REPORT_LIMIT {
+        println!("  ... and {} more", by_count.len() - UNMAPPED_REPORT_LIMIT);
+    }
+}